@@ -0,0 +1,208 @@
+//! Create dictionaries in the dict format.
+//!
+//! The [DictionaryBuilder](struct.DictionaryBuilder.html) does what `dictfmt` does for dictd: it
+//! takes entries with one or more headwords and a definition, and writes a `.dict` (or
+//! dictzipped `.dict.dz`) file with the definitions and a sorted `.index` file. The
+//! `00-database-*` meta entries are generated from a
+//! [DatabaseInfo](../metadata/struct.DatabaseInfo.html); like with `dictfmt`, their definitions
+//! start with a line holding the headword.
+//!
+//! Headwords are written to the index normalised according to the
+//! [collation](../collation/index.html) selected by the metadata, like `dictfmt` does. If the
+//! normalised headword differs from the given one, the latter is kept in a fourth column, as with
+//! `dictfmt --index-keep-orig`.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::Path;
+
+use crate::collation::Collation;
+use crate::dictreader::DictReaderRaw;
+use crate::dictwriter;
+use crate::errors::DictError;
+use crate::indexing::{self, encode_number};
+use crate::metadata::{self, DatabaseInfo};
+use crate::normalizer::Normalizer;
+use crate::Dictionary;
+
+/// Builder for dict files and their index.
+#[derive(Clone, Debug)]
+pub struct DictionaryBuilder {
+    info: DatabaseInfo,
+    /// headwords and definition of each entry, in the order they were added
+    entries: Vec<(Vec<String>, String)>,
+}
+
+/// A line of the index to be written.
+struct IndexLine {
+    key: String,
+    offset: u64,
+    length: u64,
+    original: Option<String>,
+}
+
+impl Default for DictionaryBuilder {
+    fn default() -> DictionaryBuilder {
+        DictionaryBuilder::new()
+    }
+}
+
+impl DictionaryBuilder {
+    /// Create a builder without entries.
+    ///
+    /// The metadata marks the database as encoded in UTF-8, all other fields are empty.
+    pub fn new() -> DictionaryBuilder {
+        DictionaryBuilder {
+            info: DatabaseInfo { utf8: true, ..DatabaseInfo::default() },
+            entries: Vec::new(),
+        }
+    }
+
+    /// Get the metadata to be written.
+    pub fn info(&self) -> &DatabaseInfo {
+        &self.info
+    }
+
+    /// Set the metadata, from which the `00-database-*` entries are generated.
+    ///
+    /// The `allchars` and `case_sensitive` flags also select how headwords are normalised in the
+    /// index.
+    pub fn set_info(&mut self, info: DatabaseInfo) {
+        self.info = info;
+    }
+
+    /// Add an entry with one or more headwords.
+    ///
+    /// All headwords point to the same definition. A line break is appended to the definition, if
+    /// it doesn't end with one. Headwords must not contain tabs or line breaks, since these
+    /// separate the columns and lines of the index.
+    pub fn add_entry(&mut self, headwords: &[&str], definition: &str) -> Result<(), DictError> {
+        for headword in headwords {
+            if let Some(position) = headword.find(['\t', '\n', '\r']) {
+                let ch = headword[position..].chars().next().unwrap_or('\t');
+                return Err(DictError::InvalidCharacter(ch, None, Some(position)));
+            }
+        }
+        let mut definition = definition.to_string();
+        if !definition.ends_with('\n') {
+            definition.push('\n');
+        }
+        self.entries.push((headwords.iter().map(|h| h.to_string()).collect(), definition));
+        Ok(())
+    }
+
+    /// Number of entries added so far, not counting meta entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether no entries were added.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Write the dict file and the index to the given paths.
+    ///
+    /// The dict file is compressed if its name ends on `.dz`. A headword which is empty once
+    /// normalised, e.g. one consisting only of punctuation, is reported as
+    /// `DictError::InvalidFileFormat`, since it couldn't be looked up.
+    pub fn write_files<P: AsRef<Path>>(&self, dict_path: P, index_path: P)
+            -> Result<(), DictError> {
+        let compress = dict_path.as_ref().extension() == Some(OsStr::new("dz"));
+        let mut dict = BufWriter::new(File::create(dict_path)?);
+        let mut index = BufWriter::new(File::create(index_path)?);
+        self.write(&mut dict, &mut index, compress)?;
+        dict.flush()?;
+        index.flush()?;
+        Ok(())
+    }
+
+    /// Write the dict data and the index, optionally compressing the dict data with dictzip.
+    pub fn write<D: Write, I: Write>(&self, mut dict: D, mut index: I, compress: bool)
+            -> Result<(), DictError> {
+        let (data, lines) = self.assemble()?;
+        if compress {
+            dictwriter::write_dictzip(&data, &mut dict)?;
+        } else {
+            dict.write_all(&data)?;
+        }
+        for line in lines {
+            write!(index, "{}\t{}\t{}", line.key, encode_number(line.offset),
+                    encode_number(line.length))?;
+            match line.original {
+                Some(original) => writeln!(index, "\t{}", original)?,
+                None => writeln!(index)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Create an in-memory dictionary from the entries.
+    pub fn build(&self) -> Result<Dictionary, DictError> {
+        let mut data = Vec::new();
+        let mut index = Vec::new();
+        self.write(&mut data, &mut index, false)?;
+        let reader = DictReaderRaw::new(Cursor::new(data))?;
+        let index = indexing::parse_index(Cursor::new(index))?;
+        Ok(crate::load_dictionary(Box::new(reader), index))
+    }
+
+    /// Concatenate meta entries and definitions and compute the sorted index.
+    fn assemble(&self) -> Result<(Vec<u8>, Vec<IndexLine>), DictError> {
+        let collation = Collation { allchars: self.info.allchars,
+                case_sensitive: self.info.case_sensitive };
+        let normalizer = Normalizer::default();
+        let mut data = Vec::new();
+        let mut lines = Vec::new();
+        let mut add = |headwords: &[String], definition: &str| {
+            let offset = data.len() as u64;
+            data.extend_from_slice(definition.as_bytes());
+            for headword in headwords {
+                let key = collation.normalize(&normalizer.normalize(headword));
+                if key.trim().is_empty() {
+                    return Err(DictError::InvalidFileFormat(format!("headword {:?} is empty once \
+                            normalised", headword), None));
+                }
+                let original = if key != *headword { Some(headword.clone()) } else { None };
+                lines.push(IndexLine { key, offset, length: definition.len() as u64, original });
+            }
+            Ok(())
+        };
+
+        for (name, definition) in self.meta_entries() {
+            add(&[name], &definition)?;
+        }
+        for (headwords, definition) in &self.entries {
+            add(headwords, definition)?;
+        }
+        lines.sort_by(|a, b| (&a.key, a.offset).cmp(&(&b.key, b.offset)));
+        Ok((data, lines))
+    }
+
+    /// Get the headwords and definitions of the meta entries.
+    ///
+    /// Each definition starts with the headword on a line of its own, followed by the value.
+    fn meta_entries(&self) -> Vec<(String, String)> {
+        let info = &self.info;
+        let mut entries = Vec::new();
+        let values = [(metadata::SHORT, &info.short_name), (metadata::INFO, &info.info),
+                (metadata::URL, &info.url), (metadata::ALPHABET, &info.alphabet)];
+        for (name, value) in values.iter() {
+            if let Some(value) = value {
+                entries.push((name.to_string(), format!("{}\n{}\n", name, value)));
+            }
+        }
+        // flags are set by the presence of the entry
+        let flags = [(metadata::UTF8, info.utf8), (metadata::EIGHT_BIT, info.eight_bit),
+                (metadata::ALLCHARS, info.allchars),
+                (metadata::CASE_SENSITIVE, info.case_sensitive)];
+        entries.extend(flags.iter().filter(|(_, set)| *set)
+                .map(|(name, _)| (name.to_string(), format!("{}\n", name))));
+        if let Some(ref version) = info.dictfmt_version {
+            let name = format!("{}{}", metadata::DICTFMT_PREFIX, version);
+            entries.push((name.clone(), format!("{}\n", name)));
+        }
+        entries
+    }
+}
@@ -0,0 +1,198 @@
+//! Binary cache for parsed index files.
+//!
+//! Parsing an index file means splitting every line and decoding two base64 numbers per line.
+//! For large dictionaries, this is noticeable on every start. The cache stores the parsed index in
+//! a binary format, either next to the index file (`<index>.cache`) or in a separate cache
+//! directory.
+//!
+//! A cache records the size, modification time and a hash of the index file it was created from.
+//! It is considered fresh if size and modification time still match, or if the size and the hash
+//! match (e.g. after the file was copied). Otherwise, the index is parsed again and the cache is
+//! rewritten.
+//!
+//! Caches are only used when asked for, with [load_index_cached](fn.load_index_cached.html) or
+//! [load_dictionary_from_file_cached](../fn.load_dictionary_from_file_cached.html).
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::errors::DictError;
+use crate::indexing::{self, Index};
+
+/// identification of an index cache file
+const MAGIC: &[u8; 4] = b"DIXC";
+/// version of the cache file format
+const FORMAT_VERSION: u32 = 2;
+/// file name extension of cache files
+const CACHE_EXTENSION: &str = "cache";
+
+/// Where to keep the cache for an index file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CacheLocation {
+    /// next to the index, with `.cache` appended to the file name
+    NextToIndex,
+    /// in the given directory; the file name is derived from the full path of the index
+    Directory(PathBuf),
+}
+
+impl CacheLocation {
+    /// Get the path of the cache file for the given index file.
+    pub fn cache_path<P: AsRef<Path>>(&self, index_path: P) -> PathBuf {
+        let index_path = index_path.as_ref();
+        let file_name = index_path.file_name().map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match *self {
+            CacheLocation::NextToIndex =>
+                index_path.with_file_name(format!("{}.{}", file_name, CACHE_EXTENSION)),
+            CacheLocation::Directory(ref dir) => {
+                // distinguish index files with the same name from different directories
+                let full_path = fs::canonicalize(index_path)
+                    .unwrap_or_else(|_| index_path.to_path_buf());
+                let hash = fnv1a(full_path.to_string_lossy().as_bytes());
+                dir.join(format!("{}-{:016x}.{}", file_name, hash, CACHE_EXTENSION))
+            },
+        }
+    }
+}
+
+/// Properties of an index file used to detect changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Fingerprint {
+    size: u64,
+    /// modification time in nanoseconds since the epoch, 0 if unknown
+    mtime: u128,
+    /// hash of the content, only computed if required
+    hash: Option<u64>,
+}
+
+impl Fingerprint {
+    fn of<P: AsRef<Path>>(path: P) -> Result<Fingerprint, DictError> {
+        let metadata = fs::metadata(path)?;
+        let mtime = metadata.modified().ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_nanos());
+        Ok(Fingerprint { size: metadata.len(), mtime, hash: None })
+    }
+
+    fn with_hash<P: AsRef<Path>>(mut self, path: P) -> Result<Fingerprint, DictError> {
+        if self.hash.is_none() {
+            self.hash = Some(fnv1a(&fs::read(path)?));
+        }
+        Ok(self)
+    }
+}
+
+/// Load an index using a cache.
+///
+/// If the cache for `index_path` is fresh, the index is read from it. Otherwise the index file is
+/// parsed and the cache is (re)written. Failing to write the cache, e.g. because of missing
+/// permissions, is not an error, the parsed index is returned nevertheless.
+pub fn load_index_cached<P: AsRef<Path>>(index_path: P, location: &CacheLocation)
+        -> Result<Index, DictError> {
+    let index_path = index_path.as_ref();
+    let cache_path = location.cache_path(index_path);
+    let fingerprint = Fingerprint::of(index_path)?;
+    if let Ok(Some(index)) = read_cache_if_fresh(&cache_path, index_path, &fingerprint) {
+        return Ok(index);
+    }
+
+    // hash exactly the bytes which are parsed, the file might be changed meanwhile
+    let content = fs::read(index_path)?;
+    let index = indexing::parse_index(content.as_slice())?;
+    // keep the modification time only if the file did not change while it was read, else the
+    // hash is compared when the cache is read
+    let after = Fingerprint::of(index_path)?;
+    let size = content.len() as u64;
+    let unchanged = after == fingerprint && size == fingerprint.size;
+    let mtime = if unchanged { fingerprint.mtime } else { 0 };
+    let fingerprint = Fingerprint { size, mtime, hash: Some(fnv1a(&content)) };
+    let _ = write_cache(&cache_path, &index, &fingerprint);
+    Ok(index)
+}
+
+/// Check whether a fresh cache exists for the given index file.
+pub fn is_fresh<P: AsRef<Path>>(index_path: P, location: &CacheLocation) -> bool {
+    let index_path = index_path.as_ref();
+    Fingerprint::of(index_path).ok()
+        .and_then(|fp| read_cache_if_fresh(&location.cache_path(index_path), index_path, &fp)
+                  .ok())
+        .flatten()
+        .is_some()
+}
+
+/// Read a cache, if it matches the fingerprint of the index file.
+fn read_cache_if_fresh(cache_path: &Path, index_path: &Path, current: &Fingerprint)
+        -> Result<Option<Index>, DictError> {
+    let mut reader = BufReader::new(File::open(cache_path)?);
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC || reader.read_u32::<LittleEndian>()? != FORMAT_VERSION {
+        return Ok(None);
+    }
+    let size = reader.read_u64::<LittleEndian>()?;
+    let mtime = reader.read_u128::<LittleEndian>()?;
+    let hash = reader.read_u64::<LittleEndian>()?;
+    if size != current.size {
+        return Ok(None);
+    }
+    if mtime != current.mtime || current.mtime == 0 {
+        // modified or copied, compare content
+        if current.clone().with_hash(index_path)?.hash != Some(hash) {
+            return Ok(None);
+        }
+    }
+
+    let count = reader.read_u64::<LittleEndian>()?;
+    let mut index = Index::with_capacity(count.min(1 << 20) as usize);
+    for _ in 0..count {
+        let headword = indexing::read_string(&mut reader)?;
+        let locations = indexing::read_locations(&mut reader)?;
+        index.insert(headword, locations);
+    }
+    Ok(Some(index))
+}
+
+fn write_cache(cache_path: &Path, index: &Index, fingerprint: &Fingerprint)
+        -> Result<(), DictError> {
+    if let Some(dir) = cache_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // write to a temporary file first, so that no reader ever sees a half-written cache
+    let mut tmp_path = cache_path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_u32::<LittleEndian>(FORMAT_VERSION)?;
+    writer.write_u64::<LittleEndian>(fingerprint.size)?;
+    writer.write_u128::<LittleEndian>(fingerprint.mtime)?;
+    writer.write_u64::<LittleEndian>(fingerprint.hash.unwrap_or(0))?;
+    writer.write_u64::<LittleEndian>(index.len() as u64)?;
+    for (headword, locations) in index {
+        indexing::write_string(&mut writer, headword)?;
+        indexing::write_locations(&mut writer, locations)?;
+    }
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&tmp_path, cache_path)?;
+    Ok(())
+}
+
+/// initial value of the FNV-1a hash
+pub(crate) const FNV1A_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// 64-bit FNV-1a hash; not cryptographically secure, but sufficient to detect changes.
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    fnv1a_extend(FNV1A_OFFSET_BASIS, data)
+}
+
+/// Continue an FNV-1a hash with more data.
+pub(crate) fn fnv1a_extend(mut hash: u64, data: &[u8]) -> u64 {
+    for byte in data {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
@@ -0,0 +1,138 @@
+//! Headword normalisation and collation as done by dictd.
+//!
+//! dictd does not compare headwords byte by byte. Unless a database requests otherwise, it folds
+//! case and ignores all characters which are neither alphanumeric nor white space, both when
+//! building the index (`dictfmt`) and when processing a query. Hence "A-Puero" finds the headword
+//! "apuero". Two entries in the index switch this behaviour off:
+//!
+//! -   `00-database-allchars`: keep all characters
+//! -   `00-database-case-sensitive`: do not fold case
+//!
+//! This module implements these rules, so that lookups succeed for the same queries as with
+//! dictd.
+
+use std::cmp::Ordering;
+
+use crate::indexing::Index;
+
+/// headwords indicating that all characters are significant
+const ALLCHARS_HEADWORDS: [&str; 2] = ["00-database-allchars", "00databaseallchars"];
+/// headwords indicating that case is significant
+const CASE_SENSITIVE_HEADWORDS: [&str; 2] = ["00-database-case-sensitive",
+        "00databasecasesensitive"];
+
+/// Normalisation and collation rules of a database.
+///
+/// The default corresponds to a database without any of the `00-database-allchars` and
+/// `00-database-case-sensitive` entries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Collation {
+    /// keep characters which are neither alphanumeric nor white space
+    pub allchars: bool,
+    /// do not fold case
+    pub case_sensitive: bool,
+}
+
+impl Collation {
+    /// Select the rules from the meta entries of an index.
+    ///
+    /// Both the plain and the normalised spelling of the meta headwords (`00-database-allchars`,
+    /// `00databaseallchars`) are recognised.
+    pub fn from_index(index: &Index) -> Collation {
+        let has_any = |headwords: &[&str]| headwords.iter().any(|h| index.contains_key(*h));
+        Collation {
+            allchars: has_any(&ALLCHARS_HEADWORDS),
+            case_sensitive: has_any(&CASE_SENSITIVE_HEADWORDS),
+        }
+    }
+
+    /// Normalise a headword or query.
+    ///
+    /// White space is replaced by a single space character. Unless `allchars` is set, all other
+    /// characters which are not alphanumeric are removed and unless `case_sensitive` is set, the
+    /// word is converted to lower case.
+    pub fn normalize(&self, word: &str) -> String {
+        let mut normalized = String::with_capacity(word.len());
+        for ch in word.chars() {
+            if ch.is_whitespace() {
+                normalized.push(' ');
+            } else if self.allchars || ch.is_alphanumeric() {
+                if self.case_sensitive {
+                    normalized.push(ch);
+                } else {
+                    normalized.extend(ch.to_lowercase());
+                }
+            }
+        }
+        normalized
+    }
+
+    /// Check whether `word` is already in normalised form.
+    ///
+    /// This is cheaper than comparing with the result of [normalize](#method.normalize), since
+    /// no allocation is required.
+    pub fn is_normalized(&self, word: &str) -> bool {
+        word.chars().all(|ch| if ch.is_whitespace() {
+                ch == ' '
+            } else {
+                (self.allchars || ch.is_alphanumeric())
+                    && (self.case_sensitive || ch.to_lowercase().eq(std::iter::once(ch)))
+            })
+    }
+
+    /// Compare two headwords according to the rules of the database.
+    ///
+    /// This is the order in which dictd expects headwords in its index.
+    pub fn compare(&self, first: &str, second: &str) -> Ordering {
+        self.normalize(first).cmp(&self.normalize(second))
+    }
+
+    /// Normalise all headwords of an index.
+    ///
+    /// See [indexing::normalize_index](../indexing/fn.normalize_index.html) for how headwords
+    /// which become equal are merged.
+    pub fn normalize_index(&self, index: &mut Index) {
+        crate::indexing::normalize_index(index, |w| self.normalize(w));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_rules_fold_case_and_drop_punctuation() {
+        let collation = Collation::default();
+        assert_eq!(collation.normalize("A-Puero"), "apuero");
+        assert_eq!(collation.normalize("A\tPuero!"), "a puero");
+        assert_eq!(collation.normalize("Évidence"), "évidence");
+        assert!(collation.is_normalized("a puero"));
+        assert!(!collation.is_normalized("a-puero"));
+        assert!(!collation.is_normalized("Puero"));
+    }
+
+    #[test]
+    fn test_allchars_and_case_sensitive_keep_characters() {
+        let collation = Collation { allchars: true, case_sensitive: true };
+        assert_eq!(collation.normalize("A-Puero"), "A-Puero");
+        assert!(collation.is_normalized("A-Puero"));
+        let collation = Collation { allchars: true, case_sensitive: false };
+        assert_eq!(collation.normalize("A-Puero"), "a-puero");
+    }
+
+    #[test]
+    fn test_comparison_ignores_case_and_punctuation() {
+        let collation = Collation::default();
+        assert_eq!(collation.compare("a-b", "AB"), Ordering::Equal);
+        assert_eq!(collation.compare("ab", "Ac"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_headwords_of_an_index_are_normalised() {
+        let mut index = Index::new();
+        index.insert("A-Puero".into(), vec![crate::indexing::Location::new(0, 1)]);
+        Collation::default().normalize_index(&mut index);
+        assert_eq!(index["apuero"][0].original.as_deref(), Some("A-Puero"));
+    }
+}
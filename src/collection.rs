@@ -0,0 +1,402 @@
+//! Look up words in several databases at once.
+//!
+//! A dictd server offers several databases, each with a name, e.g. `fd-lat-deu`. Clients either
+//! name the database to search or use one of two special names: `*` searches all databases and
+//! returns the results of each, `!` searches the databases in order and stops at the first one
+//! with a result. A [DictionaryCollection](struct.DictionaryCollection.html) keeps named
+//! dictionaries in the order they were added and answers lookups and matches the same way.
+//!
+//! Databases can be added with a [Loader](type.Loader.html) instead of a dictionary; they are
+//! opened on first use, so that a server with many databases starts quickly. A database which
+//! fails to open is not tried again; `*` and `!` skip it and it is listed by
+//! [failures](struct.DictionaryCollection.html#method.failures). Like with
+//! `database_exit` in the configuration of dictd, databases added after
+//! [insert_exit](struct.DictionaryCollection.html#method.insert_exit) are only searched when
+//! named explicitly.
+//!
+//! Virtual databases group other databases of the collection under a new name, like
+//! `database_virtual` of dictd. Lookups and matches in a virtual database search its members in
+//! order; each result names the member database it comes from.
+
+use crate::errors::DictError;
+use crate::matching::MatchOptions;
+use crate::Dictionary;
+
+/// name selecting all databases of a collection
+pub const ALL_DATABASES: &str = "*";
+/// name selecting the first database of a collection with a result
+pub const FIRST_MATCH: &str = "!";
+
+/// A definition found in a database of a collection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Definition {
+    /// name of the database in the collection
+    pub database: String,
+    /// short description of the database, its name if it has none
+    pub description: String,
+    /// headword in its original spelling, if known, else as normalised in the index
+    pub headword: String,
+    /// the definition
+    pub definition: String,
+}
+
+/// A headword matched in a database of a collection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Match {
+    /// name of the database in the collection
+    pub database: String,
+    /// the matching headword
+    pub headword: String,
+}
+
+/// Function opening a database on first use.
+pub type Loader = Box<dyn FnMut() -> Result<Dictionary, DictError>>;
+
+/// A dictionary, the means to open it or the members of a virtual database.
+enum State {
+    Loaded(Box<Dictionary>),
+    Pending(Loader),
+    /// the loader failed with the given error message
+    Failed(String),
+    Virtual { members: Vec<String>, description: Option<String> },
+}
+
+/// A named database of a collection.
+struct Database {
+    name: String,
+    state: State,
+    /// added after the exit, so not searched by `*` and `!`
+    after_exit: bool,
+}
+
+impl Database {
+    /// Get the dictionary, opening it if required.
+    ///
+    /// If opening fails, the error is returned and the loader is dropped; later uses yield
+    /// `DictError::UnavailableDatabase`. Virtual databases have no dictionary,
+    /// `DictError::UnknownDatabase` is returned for them.
+    fn open(&mut self) -> Result<&mut Dictionary, DictError> {
+        if let State::Pending(ref mut loader) = self.state {
+            match loader() {
+                Ok(dictionary) => self.state = State::Loaded(Box::new(dictionary)),
+                Err(e) => {
+                    self.state = State::Failed(e.to_string());
+                    return Err(e);
+                },
+            }
+        }
+        match self.state {
+            State::Loaded(ref mut dictionary) => Ok(dictionary.as_mut()),
+            State::Pending(_) => unreachable!("the database was opened above"),
+            State::Failed(ref reason) =>
+                Err(DictError::UnavailableDatabase(self.name.clone(), reason.clone())),
+            State::Virtual { .. } => Err(DictError::UnknownDatabase(self.name.clone())),
+        }
+    }
+
+    fn is_virtual(&self) -> bool {
+        matches!(self.state, State::Virtual { .. })
+    }
+}
+
+/// Named dictionaries, kept in the order they were added.
+#[derive(Default)]
+pub struct DictionaryCollection {
+    databases: Vec<Database>,
+    /// whether the exit has been inserted
+    exit: bool,
+}
+
+impl DictionaryCollection {
+    /// Create an empty collection.
+    pub fn new() -> DictionaryCollection {
+        DictionaryCollection::default()
+    }
+
+    /// Add a dictionary under the given name.
+    ///
+    /// If there already is a dictionary with this name, it is replaced and returned; the position
+    /// of the database within the collection is kept.
+    pub fn insert<S: Into<String>>(&mut self, name: S, dictionary: Dictionary)
+            -> Option<Dictionary> {
+        self.insert_state(name.into(), State::Loaded(Box::new(dictionary)))
+    }
+
+    /// Add a database which is opened by `loader` on first use.
+    ///
+    /// Like [insert](#method.insert), a database with the same name is replaced. If the loader
+    /// fails, the error is returned from the lookup which needed the database; the loader is not
+    /// called again.
+    pub fn insert_lazy<S, F>(&mut self, name: S, loader: F)
+            where S: Into<String>, F: FnMut() -> Result<Dictionary, DictError> + 'static {
+        self.insert_state(name.into(), State::Pending(Box::new(loader)));
+    }
+
+    /// Add a virtual database, grouping the given member databases.
+    ///
+    /// Members are searched in the given order and must be databases with a dictionary; they may
+    /// be added before or after the virtual database. Virtual databases are not searched by `*`
+    /// and `!`, which search all their members anyway. The description is reported by
+    /// [descriptions](#method.descriptions); without one, the name is used.
+    pub fn insert_virtual<S: Into<String>>(&mut self, name: S, members: Vec<String>,
+            description: Option<String>) {
+        self.insert_state(name.into(), State::Virtual { members, description });
+    }
+
+    /// Add or replace a database; the dictionary of a replaced database is returned.
+    fn insert_state(&mut self, name: String, state: State) -> Option<Dictionary> {
+        match self.position(&name) {
+            Some(position) => match std::mem::replace(&mut self.databases[position].state, state) {
+                State::Loaded(dictionary) => Some(*dictionary),
+                _ => None,
+            },
+            None => {
+                self.databases.push(Database { name, state, after_exit: self.exit });
+                None
+            },
+        }
+    }
+
+    /// Exclude all databases added from now on from searches of `*` and `!`.
+    ///
+    /// This corresponds to `database_exit` in the configuration of dictd. The databases can still
+    /// be searched by their name.
+    pub fn insert_exit(&mut self) {
+        self.exit = true;
+    }
+
+    /// Remove the database with the given name.
+    ///
+    /// Its dictionary is returned, if it has been opened.
+    pub fn remove(&mut self, name: &str) -> Option<Dictionary> {
+        match self.databases.remove(self.position(name)?).state {
+            State::Loaded(dictionary) => Some(*dictionary),
+            _ => None,
+        }
+    }
+
+    /// Get the dictionary with the given name, if it has been opened already.
+    pub fn get(&self, name: &str) -> Option<&Dictionary> {
+        match self.databases.iter().find(|db| db.name == name)?.state {
+            State::Loaded(ref dictionary) => Some(dictionary.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Get the dictionary with the given name for lookups, if it has been opened already.
+    ///
+    /// Use [open](#method.open) to open it if required.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Dictionary> {
+        match self.databases.iter_mut().find(|db| db.name == name)?.state {
+            State::Loaded(ref mut dictionary) => Some(dictionary.as_mut()),
+            _ => None,
+        }
+    }
+
+    /// Get the names of the databases which could not be opened, with the error message.
+    pub fn failures(&self) -> Vec<(&str, &str)> {
+        self.databases.iter()
+            .filter_map(|db| match db.state {
+                State::Failed(ref reason) => Some((db.name.as_str(), reason.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Get the members of the virtual database with the given name.
+    ///
+    /// `None` is returned if there is no such database or if it is not virtual.
+    pub fn members(&self, name: &str) -> Option<&[String]> {
+        match self.databases.iter().find(|db| db.name == name)?.state {
+            State::Virtual { ref members, .. } => Some(members.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Get the dictionary with the given name, opening it if required.
+    ///
+    /// Virtual databases have no dictionary of their own, `DictError::UnknownDatabase` is
+    /// returned for them like for names not in the collection.
+    pub fn open(&mut self, name: &str) -> Result<&mut Dictionary, DictError> {
+        match self.databases.iter_mut().find(|db| db.name == name) {
+            Some(db) => db.open(),
+            None => Err(DictError::UnknownDatabase(name.into())),
+        }
+    }
+
+    /// Check whether the database with the given name has been opened.
+    pub fn is_open(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Check whether there is a database with the given name.
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// Names of the databases, in order.
+    pub fn names(&self) -> Vec<&str> {
+        self.databases.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    /// Number of databases.
+    pub fn len(&self) -> usize {
+        self.databases.len()
+    }
+
+    /// Check whether the collection has no databases.
+    pub fn is_empty(&self) -> bool {
+        self.databases.is_empty()
+    }
+
+    /// Get the names and short descriptions of all databases, like `SHOW DB` of dictd.
+    ///
+    /// Databases without a short description are listed with their name, as are databases which
+    /// could not be opened. All databases are opened for this.
+    pub fn descriptions(&mut self) -> Result<Vec<(String, String)>, DictError> {
+        self.databases.iter_mut()
+            .map(|db| {
+                let name = db.name.clone();
+                let description = match db.state {
+                    State::Virtual { ref description, .. } =>
+                        description.clone().unwrap_or_else(|| name.clone()),
+                    _ => match db.open() {
+                        Ok(dictionary) => description(&name, dictionary)?,
+                        Err(_) => name.clone(),
+                    },
+                };
+                Ok((name, description))
+            })
+            .collect()
+    }
+
+    /// Look up a word in the named database, in all databases (`*`) or in the first database
+    /// which has it (`!`).
+    ///
+    /// A virtual database is searched like `*`, restricted to its members. Definitions are
+    /// returned in the order of the databases, tagged with the name and short description of the
+    /// database they were found in. If no database has the word, `DictError::WordNotFound` is
+    /// returned; an unknown database name yields `DictError::UnknownDatabase`. Databases are
+    /// opened as they are searched; if several databases are searched, those which cannot be
+    /// opened or fail to read the definitions are skipped. Their error is only returned if no
+    /// database could be searched at all.
+    pub fn lookup(&mut self, database: &str, word: &str) -> Result<Vec<Definition>, DictError> {
+        let first_only = database == FIRST_MATCH;
+        let (databases, skip_failures) = self.select(database)?;
+        let definitions = search(databases, skip_failures, first_only, |name, dictionary| {
+            let found = match dictionary.lookup_all_with_headwords(word) {
+                Ok(found) => found,
+                Err(DictError::WordNotFound(_)) => return Ok(Vec::new()),
+                Err(e) => return Err(e),
+            };
+            let description = description(name, dictionary)?;
+            Ok(found.into_iter().map(|(headword, definition)| Definition {
+                database: name.to_string(),
+                description: description.clone(),
+                headword,
+                definition,
+            }).collect())
+        })?;
+        if definitions.is_empty() {
+            return Err(DictError::WordNotFound(word.into()));
+        }
+        Ok(definitions)
+    }
+
+    /// Match headwords with the named strategy in the named database, in all databases (`*`) or
+    /// in the first database with a match (`!`).
+    ///
+    /// A virtual database is searched like `*`, restricted to its members. Matches are returned
+    /// in the order of the databases, each tagged with the database it was found in. Like with
+    /// [lookup](#method.lookup), databases which cannot be opened or searched are skipped if
+    /// several are searched, and `DictError::WordNotFound` is returned if no database has a
+    /// match. See [Dictionary::match_words](../struct.Dictionary.html#method.match_words).
+    pub fn match_words(&mut self, database: &str, strategy: &str, query: &str,
+            options: &MatchOptions) -> Result<Vec<Match>, DictError> {
+        let first_only = database == FIRST_MATCH;
+        let (databases, skip_failures) = self.select(database)?;
+        let matches = search(databases, skip_failures, first_only, |name, dictionary|
+            Ok(dictionary.match_words(strategy, query, options)?.into_iter()
+                .map(|headword| Match { database: name.to_string(), headword })
+                .collect()))?;
+        if matches.is_empty() {
+            return Err(DictError::WordNotFound(query.into()));
+        }
+        Ok(matches)
+    }
+
+    /// Get the databases to search for the given database name and whether databases which
+    /// cannot be opened are to be skipped.
+    ///
+    /// The members of a virtual database are returned in their order, each only once. Failures
+    /// are skipped for `*`, `!` and virtual databases.
+    fn select(&mut self, database: &str) -> Result<(Vec<&mut Database>, bool), DictError> {
+        if database == ALL_DATABASES || database == FIRST_MATCH {
+            return Ok((self.databases.iter_mut().filter(|db| !db.after_exit && !db.is_virtual())
+                    .collect(), true));
+        }
+        let position = self.position(database)
+            .ok_or_else(|| DictError::UnknownDatabase(database.into()))?;
+        let is_virtual = self.databases[position].is_virtual();
+        let positions = match self.databases[position].state {
+            State::Virtual { ref members, .. } => members.iter()
+                .map(|member| self.position(member)
+                    .filter(|p| !self.databases[*p].is_virtual())
+                    .ok_or_else(|| DictError::UnknownDatabase(member.clone())))
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => vec![position],
+        };
+        let mut databases: Vec<Option<&mut Database>> = self.databases.iter_mut().map(Some)
+            .collect();
+        Ok((positions.into_iter().filter_map(|p| databases[p].take()).collect(), is_virtual))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.databases.iter().position(|db| db.name == name)
+    }
+}
+
+/// Get the short description of a database, falling back to its name.
+fn description(name: &str, dictionary: &mut Dictionary) -> Result<String, DictError> {
+    match dictionary.short_name() {
+        Ok(short_name) => Ok(short_name),
+        Err(DictError::WordNotFound(_)) => Ok(name.to_string()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Search the given databases in order, stopping at the first one with a result if `first_only`
+/// is set.
+///
+/// With `skip_failures`, databases which cannot be opened or searched are skipped; the first of
+/// these errors is only returned if no database could be searched.
+fn search<T, F>(databases: Vec<&mut Database>, skip_failures: bool, first_only: bool,
+        mut search_one: F) -> Result<Vec<T>, DictError>
+        where F: FnMut(&str, &mut Dictionary) -> Result<Vec<T>, DictError> {
+    let mut results = Vec::new();
+    let mut first_error = None;
+    let mut searched = false;
+    for db in databases {
+        let name = db.name.clone();
+        let found = match db.open().and_then(|dictionary| search_one(&name, dictionary)) {
+            Ok(found) => found,
+            Err(e) if skip_failures => {
+                first_error.get_or_insert(e);
+                continue;
+            },
+            Err(e) => return Err(e),
+        };
+        searched = true;
+        let found_any = !found.is_empty();
+        results.extend(found);
+        if first_only && found_any {
+            break;
+        }
+    }
+    match first_error {
+        Some(e) if !searched => Err(e),
+        _ => Ok(results),
+    }
+}
@@ -0,0 +1,445 @@
+//! Read the configuration of dictd.
+//!
+//! dictd is configured in `dictd.conf`, which lists the databases to serve and who may access
+//! them:
+//!
+//! ```text
+//! access { allow 127.0.0.1 }
+//! site "/etc/dictd/site.txt"
+//!
+//! database fd-lat-deu {
+//!     data "/usr/share/dictd/freedict-lat-deu.dict.dz"
+//!     index "/usr/share/dictd/freedict-lat-deu.index"
+//! }
+//! database_exit
+//! database_virtual latin {
+//!     name "All Latin dictionaries"
+//!     database_list "fd-lat-deu,fd-lat-eng"
+//! }
+//! ```
+//!
+//! Statements end at a line break or a semicolon; values with spaces are enclosed in double
+//! quotes and comments start with `#`; `include FILE` reads the statements of another file at its
+//! place. [parse_config](fn.parse_config.html) reads such a file
+//! into a [Config](struct.Config.html), from which a
+//! [DictionaryCollection](../collection/struct.DictionaryCollection.html) with the configured
+//! databases is built. The `name` and `info` of a database replace the short and long
+//! description from its `00-database-*` entries. Virtual databases become virtual databases of
+//! the collection, described by their `name`.
+//!
+//! Access rules, the site file, users and the `global` and `limits` blocks are parsed, but it is
+//! up to the server to enforce them. Plugin databases (`database_plugin`) cannot be loaded and
+//! are only listed.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::collection::DictionaryCollection;
+use crate::errors::DictError;
+use crate::Dictionary;
+
+/// deepest nesting of included files, so that files including each other are detected
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Configuration of a dictd server.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// databases in the order of the configuration
+    pub databases: Vec<DatabaseConfig>,
+    /// number of databases before `database_exit`, if given; the others are only searched when
+    /// named explicitly
+    pub exit: Option<usize>,
+    /// rules of the top-level `access` block
+    pub access: Vec<AccessRule>,
+    /// path of the file shown by `SHOW SERVER`
+    pub site: Option<String>,
+    /// users with their shared secret
+    pub users: Vec<(String, String)>,
+    /// settings of the `global` and `limits` blocks, e.g. `("port", ["2628"])`
+    pub global: Vec<(String, Vec<String>)>,
+}
+
+/// A database of the configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DatabaseConfig {
+    /// name of the database as used by clients
+    pub name: String,
+    /// files or members of the database
+    pub kind: DatabaseKind,
+    /// short description (`name`), replacing the one of the database
+    pub short_name: Option<String>,
+    /// long description (`info`), replacing the one of the database
+    pub info: Option<String>,
+    /// rules of the `access` block of this database
+    pub access: Vec<AccessRule>,
+    /// database is not listed by `SHOW DB`
+    pub invisible: bool,
+    /// other settings, e.g. `("filter", ["gunzip"])` or `("default_strategy", ["prefix"])`
+    pub options: Vec<(String, Vec<String>)>,
+}
+
+impl DatabaseConfig {
+    /// Create a database without descriptions, access rules or other settings.
+    pub fn new(name: &str, kind: DatabaseKind) -> DatabaseConfig {
+        DatabaseConfig { name: name.to_string(), kind, short_name: None, info: None,
+                access: Vec::new(), invisible: false, options: Vec::new() }
+    }
+}
+
+/// The kind of a configured database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DatabaseKind {
+    /// a database with a dict file and an index (`database`)
+    Files {
+        /// path of the dict file (`data`)
+        data: PathBuf,
+        /// path of the index (`index`)
+        index: PathBuf,
+    },
+    /// a group of other databases (`database_virtual`)
+    Virtual {
+        /// names of the member databases (`database_list`)
+        members: Vec<String>,
+    },
+    /// a database served by a plugin (`database_plugin`), which cannot be loaded
+    Plugin,
+}
+
+/// A rule of an `access` block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AccessRule {
+    /// allow clients matching the pattern (`allow`)
+    Allow(String),
+    /// deny clients matching the pattern (`deny`)
+    Deny(String),
+    /// allow only authenticated users (`authonly`)
+    AuthOnly,
+    /// allow the given user (`user`)
+    User(String),
+}
+
+/// A statement of the configuration: words and an optional block.
+#[derive(Debug)]
+struct Statement {
+    line: usize,
+    words: Vec<String>,
+    block: Option<Vec<Statement>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Open,
+    Close,
+    /// line break or semicolon
+    End,
+}
+
+/// Parse a dictd configuration.
+///
+/// Relative paths of included files are resolved against the current directory.
+pub fn parse_config<R: Read>(mut reader: R) -> Result<Config, DictError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    parse_text(&text, Path::new(""), 0)
+}
+
+/// Parse a dictd configuration file.
+///
+/// Relative paths of dict files, indexes and included files are resolved against the directory
+/// of the configuration file which names them.
+pub fn parse_config_file<P: AsRef<Path>>(path: P) -> Result<Config, DictError> {
+    parse_file(path.as_ref(), 0)
+}
+
+fn parse_file(path: &Path, depth: usize) -> Result<Config, DictError> {
+    let mut text = String::new();
+    File::open(path)?.read_to_string(&mut text)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_text(&text, directory, depth).map_err(|e| match e {
+        DictError::InvalidFileFormat(explanation, None) => DictError::InvalidFileFormat(
+                explanation, Some(format!("{}: ", path.display()))),
+        e => e,
+    })
+}
+
+/// Parse a configuration whose relative paths are relative to `directory`.
+fn parse_text(text: &str, directory: &Path, depth: usize) -> Result<Config, DictError> {
+    let tokens = tokenize(text)?;
+    let mut position = 0;
+    let statements = parse_statements(&tokens, &mut position, false)?;
+
+    let mut config = Config::default();
+    for statement in &statements {
+        let words: Vec<&str> = statement.words.iter().map(|w| w.as_str()).collect();
+        match (&words[..], &statement.block) {
+            (["database", name], Some(block)) => {
+                let mut database = parse_database(name, block, statement.line, false)?;
+                if let DatabaseKind::Files { ref mut data, ref mut index } = database.kind {
+                    *data = directory.join(&*data);
+                    *index = directory.join(&*index);
+                }
+                config.databases.push(database);
+            },
+            (["database_virtual", name], Some(block)) => config.databases.push(
+                    parse_database(name, block, statement.line, true)?),
+            (["database_plugin", name], Some(_)) => config.databases.push(
+                    DatabaseConfig::new(name, DatabaseKind::Plugin)),
+            (["database_exit"], None) => config.exit = Some(config.databases.len()),
+            (["access"], Some(block)) => config.access = parse_access(block)?,
+            (["site", site], None) => config.site = Some(site.to_string()),
+            (["user", name, secret], None) => config.users.push((name.to_string(),
+                    secret.to_string())),
+            (["global"], Some(block)) | (["limits"], Some(block)) => {
+                for setting in block {
+                    let (name, values) = setting_of(setting)?;
+                    config.global.push((name, values));
+                }
+            },
+            (["include", file], None) => {
+                if depth >= MAX_INCLUDE_DEPTH {
+                    return Err(invalid(statement.line, &format!("include of {:?} is nested \
+                            too deeply", file)));
+                }
+                config.append(parse_file(&directory.join(file), depth + 1)?);
+            },
+            _ => return Err(invalid(statement.line, &format!("unknown statement {:?}",
+                    statement.words.join(" ")))),
+        }
+    }
+    Ok(config)
+}
+
+impl Config {
+    /// Add the statements of an included configuration.
+    fn append(&mut self, included: Config) {
+        if self.exit.is_none() {
+            self.exit = included.exit.map(|exit| self.databases.len() + exit);
+        }
+        self.databases.extend(included.databases);
+        self.access.extend(included.access);
+        if included.site.is_some() {
+            self.site = included.site;
+        }
+        self.users.extend(included.users);
+        self.global.extend(included.global);
+    }
+
+    /// Load all databases with files into a collection, along with the virtual databases.
+    ///
+    /// The first database which cannot be loaded stops the loading, its error is returned.
+    /// Plugin databases are not part of the collection.
+    pub fn load(&self) -> Result<DictionaryCollection, DictError> {
+        self.build(false)
+    }
+
+    /// Create a collection of all databases with files and the virtual databases, opening each
+    /// database on first use.
+    ///
+    /// Errors in the files of a database are reported by the first lookup needing it; later,
+    /// `*` and `!` skip the database.
+    pub fn load_lazy(&self) -> Result<DictionaryCollection, DictError> {
+        self.build(true)
+    }
+
+    fn build(&self, lazy: bool) -> Result<DictionaryCollection, DictError> {
+        let mut collection = DictionaryCollection::new();
+        for (position, database) in self.databases.iter().enumerate() {
+            if self.exit == Some(position) {
+                collection.insert_exit();
+            }
+            let (data, index) = match database.kind {
+                DatabaseKind::Files { ref data, ref index } => (data.clone(), index.clone()),
+                DatabaseKind::Virtual { ref members } => {
+                    collection.insert_virtual(database.name.clone(), members.clone(),
+                            database.short_name.clone());
+                    continue;
+                },
+                DatabaseKind::Plugin => continue,
+            };
+            let (short_name, info) = (database.short_name.clone(), database.info.clone());
+            let loader = move || open_database(&data, &index, &short_name, &info);
+            if lazy {
+                collection.insert_lazy(database.name.clone(), loader);
+            } else {
+                collection.insert(database.name.clone(), loader()?);
+            }
+        }
+        Ok(collection)
+    }
+}
+
+/// Load a database and replace its descriptions by the configured ones.
+fn open_database(data: &Path, index: &Path, short_name: &Option<String>, info: &Option<String>)
+        -> Result<Dictionary, DictError> {
+    let mut dictionary = crate::load_dictionary_from_file(data, index)?;
+    if short_name.is_some() || info.is_some() {
+        let mut database_info = dictionary.info()?;
+        if short_name.is_some() {
+            database_info.short_name = short_name.clone();
+        }
+        if info.is_some() {
+            database_info.info = info.clone();
+        }
+        dictionary.set_info(database_info);
+    }
+    Ok(dictionary)
+}
+
+fn parse_database(name: &str, block: &[Statement], line: usize, is_virtual: bool)
+        -> Result<DatabaseConfig, DictError> {
+    let mut database = DatabaseConfig::new(name, DatabaseKind::Plugin);
+    let (mut data, mut index, mut members) = (None, None, None);
+    for statement in block {
+        let words: Vec<&str> = statement.words.iter().map(|w| w.as_str()).collect();
+        match (&words[..], &statement.block) {
+            (["data", path], None) if !is_virtual => data = Some(PathBuf::from(path)),
+            (["index", path], None) if !is_virtual => index = Some(PathBuf::from(path)),
+            (["database_list", list], None) if is_virtual => members = Some(list.split(',')
+                    .map(|m| m.trim().to_string()).filter(|m| !m.is_empty()).collect()),
+            (["name", short_name], None) => database.short_name = Some(short_name.to_string()),
+            (["info", info], None) => database.info = Some(info.to_string()),
+            (["access"], Some(block)) => database.access = parse_access(block)?,
+            (["invisible"], None) => database.invisible = true,
+            _ => database.options.push(setting_of(statement)?),
+        }
+    }
+    database.kind = if is_virtual {
+        DatabaseKind::Virtual { members: members.ok_or_else(|| invalid(line,
+                &format!("virtual database {} has no database_list", name)))? }
+    } else {
+        match (data, index) {
+            (Some(data), Some(index)) => DatabaseKind::Files { data, index },
+            _ => return Err(invalid(line, &format!("database {} needs both data and index",
+                    name))),
+        }
+    };
+    Ok(database)
+}
+
+fn parse_access(block: &[Statement]) -> Result<Vec<AccessRule>, DictError> {
+    block.iter().map(|statement| {
+        let words: Vec<&str> = statement.words.iter().map(|w| w.as_str()).collect();
+        match (&words[..], &statement.block) {
+            (["allow", pattern], None) => Ok(AccessRule::Allow(pattern.to_string())),
+            (["deny", pattern], None) => Ok(AccessRule::Deny(pattern.to_string())),
+            (["authonly"], None) => Ok(AccessRule::AuthOnly),
+            (["user", name], None) => Ok(AccessRule::User(name.to_string())),
+            _ => Err(invalid(statement.line, &format!("invalid access rule {:?}",
+                    statement.words.join(" ")))),
+        }
+    }).collect()
+}
+
+/// Get name and values of a simple statement.
+fn setting_of(statement: &Statement) -> Result<(String, Vec<String>), DictError> {
+    if statement.block.is_some() {
+        return Err(invalid(statement.line, &format!("unexpected block after {:?}",
+                statement.words.join(" "))));
+    }
+    Ok((statement.words[0].clone(), statement.words[1..].to_vec()))
+}
+
+/// Parse statements up to the end of the input or, within a block, the closing brace.
+fn parse_statements(tokens: &[(usize, Token)], position: &mut usize, in_block: bool)
+        -> Result<Vec<Statement>, DictError> {
+    let mut statements = Vec::new();
+    loop {
+        let (line, token) = match tokens.get(*position) {
+            Some((line, token)) => (*line, token),
+            None if in_block => return Err(invalid(tokens.last().map_or(1, |t| t.0),
+                    "missing }")),
+            None => return Ok(statements),
+        };
+        *position += 1;
+        let first = match token {
+            Token::End => continue,
+            Token::Close if in_block => return Ok(statements),
+            Token::Close => return Err(invalid(line, "unexpected }")),
+            Token::Open => return Err(invalid(line, "unexpected {")),
+            Token::Word(word) => word.clone(),
+        };
+        let mut words = vec![first];
+        while let Some((_, Token::Word(word))) = tokens.get(*position) {
+            words.push(word.clone());
+            *position += 1;
+        }
+        // the block may start on the next line
+        let mut next = *position;
+        while let Some((_, Token::End)) = tokens.get(next) {
+            next += 1;
+        }
+        let block = match tokens.get(next) {
+            Some((_, Token::Open)) => {
+                *position = next + 1;
+                Some(parse_statements(tokens, position, true)?)
+            },
+            _ => None,
+        };
+        statements.push(Statement { line, words, block });
+    }
+}
+
+/// Split the configuration into tokens, each with its line number.
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, DictError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\n' | ';' => tokens.push((line, Token::End)),
+            '{' => tokens.push((line, Token::Open)),
+            '}' => tokens.push((line, Token::Close)),
+            '#' => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            },
+            '"' => {
+                let start = line;
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => word.push('\n'),
+                            Some('t') => word.push('\t'),
+                            Some(escaped) => word.push(escaped),
+                            None => return Err(invalid(start, "unterminated string")),
+                        },
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            word.push(c);
+                        },
+                        None => return Err(invalid(start, "unterminated string")),
+                    }
+                }
+                tokens.push((start, Token::Word(word)));
+            },
+            c if c.is_whitespace() => (),
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{};#\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push((line, Token::Word(word)));
+            },
+        }
+        if ch == '\n' {
+            line += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+fn invalid(line: usize, explanation: &str) -> DictError {
+    DictError::InvalidFileFormat(format!("line {} of configuration: {}", line, explanation),
+            None)
+}
@@ -20,16 +20,16 @@ use crate::errors::DictError;
 
 /// limit size of a word buffer, so that malicious index files cannot request too much memory for a
 /// translation
-pub static MAX_BYTES_FOR_BUFFER: u64 = 1_048_576; // no headword definition is larger than 1M
+pub const MAX_BYTES_FOR_BUFFER: u64 = 1_048_576; // no headword definition is larger than 1M
 
 /// byte mask to query for existence of FEXTRA field in the flags byte of a `.dz` file
-pub static GZ_FEXTRA: u8 = 0b0000_0100;
+pub const GZ_FEXTRA: u8 = 0b0000_0100;
 /// byte mask to query for the existence of a file name in a `.dz` file
-pub static GZ_FNAME: u8   = 0b0000_1000; // indicates whether a file name is contained in the archive
+pub const GZ_FNAME: u8   = 0b0000_1000; // indicates whether a file name is contained in the archive
 /// byte mask to query for the existence of a comment in a `.dz` file
-pub static GZ_COMMENT: u8 = 0b0001_0000; // indicates, whether a comment is present
+pub const GZ_COMMENT: u8 = 0b0001_0000; // indicates, whether a comment is present
 /// byte mask to detect that a comment is contained in a `.dz` file
-pub static GZ_FHCRC: u8   = 0b0000_0010;
+pub const GZ_FHCRC: u8   = 0b0000_0010;
 
 
 /// .dict file format: either compressed or uncompressed
@@ -42,6 +42,24 @@ pub static GZ_FHCRC: u8   = 0b0000_0010;
 pub trait DictReader {
     /// fetch the definition from the dictionary at offset and length
     fn fetch_definition(&mut self, start_offset: u64, length: u64) -> Result<String, DictError>;
+
+    /// fetch the raw bytes from the dictionary at offset and length
+    ///
+    /// Unlike [fetch_definition](#tymethod.fetch_definition), the data isn't required to be valid
+    /// UTF-8, e.g. for binary fields of StarDict definitions. The default implementation fetches
+    /// the definition and returns its bytes.
+    fn fetch_bytes(&mut self, start_offset: u64, length: u64) -> Result<Vec<u8>, DictError> {
+        self.fetch_definition(start_offset, length).map(String::into_bytes)
+    }
+
+    /// length of the (uncompressed) dictionary content in bytes, if known
+    ///
+    /// It is used to [validate](../struct.Dictionary.html#method.validate) the index. The default
+    /// implementation returns `None`; definitions beyond the end of the data are then only
+    /// detected when reading them fails.
+    fn total_length(&self) -> Option<u64> {
+        None
+    }
 }
 
 /// Raw Dict reader
@@ -63,6 +81,10 @@ impl<B: Read + Seek> DictReaderRaw<B> {
 impl<B: Read + Seek> DictReader for DictReaderRaw<B> {
     /// fetch definition from dictionary
     fn fetch_definition(&mut self, start_offset: u64, length: u64) -> Result<String, DictError> {
+        Ok(String::from_utf8(self.fetch_bytes(start_offset, length)?)?)
+    }
+
+    fn fetch_bytes(&mut self, start_offset: u64, length: u64) -> Result<Vec<u8>, DictError> {
         if length > MAX_BYTES_FOR_BUFFER {
             return Err(DictError::MemoryError);
         }
@@ -78,7 +100,11 @@ impl<B: Read + Seek> DictReader for DictReaderRaw<B> {
             return Err(DictError::IoError(io::Error::new(
                             io::ErrorKind::UnexpectedEof, "seek beyond end of file")));
         }
-        Ok(String::from_utf8(read_data)?)
+        Ok(read_data)
+    }
+
+    fn total_length(&self) -> Option<u64> {
+        Some(self.total_length)
     }
 }
 
@@ -122,11 +148,14 @@ pub struct DictReaderDz<B: Read + Seek> {
     chunk_offsets: Vec<usize>,
     /// total size of uncompressed file
     ufile_length: u64, // has u64 to be quicker in comparing to offsets
+    /// most recently inflated chunk with its number; definitions are often read in file order
+    last_chunk: Option<(usize, Vec<u8>)>,
 }
 
 #[derive(Debug)]
-// a (GZ) chunk, representing length and offset withing the compressed file
+// a (GZ) chunk, representing its number, length and offset withing the compressed file
 struct Chunk {
+    id: usize,
     offset: usize,
     length: usize,
 }
@@ -211,13 +240,12 @@ impl<B: Read + Seek> DictReaderDz<B> {
         // save length of each compressed chunk
         let mut chunk_offsets = Vec::with_capacity(chunk_count as usize);
         // save position of last compressed byte (this is NOT EOF, could be followed by CRC checksum)
-        let mut end_compressed_data = buffered_dzdict.seek(SeekFrom::Current(0))? as usize;
+        let mut end_compressed_data = buffered_dzdict.stream_position()? as usize;
         // after the various header bytes parsed above, the list of chunk lengths can be found (slice for easier indexing)
         let chunks_from_header = &fextra[10usize..(10 + chunk_count * 2) as usize];
 
         // iterate over each 2nd byte, parse u16
         for index in (0..chunks_from_header.len()).filter(|i| (i%2)==0) {
-            let index = index as usize;
             let compressed_len = LittleEndian::read_u16(&chunks_from_header[index..(index + 2)]) as usize;
             chunk_offsets.push(end_compressed_data);
             end_compressed_data += compressed_len;
@@ -225,27 +253,29 @@ impl<B: Read + Seek> DictReaderDz<B> {
         assert_eq!(chunk_offsets.len() as u16, chunk_count, "The read number of compressed chunks in \
                 the .dz file must be equivalent to the number of chunks actually found in the file.\n");
 
-        // read uncompressed file length
-        buffered_dzdict.seek(SeekFrom::Start(end_compressed_data as u64))?;
-        let uncompressed = buffered_dzdict.read_i32::<LittleEndian>()?;
+        // read uncompressed file length (ISIZE), the last field of the gzip trailer after CRC32
+        buffered_dzdict.seek(SeekFrom::End(-4))?;
+        let uncompressed = buffered_dzdict.read_u32::<LittleEndian>()?;
 
         Ok(DictReaderDz { dzdict: buffered_dzdict.into_inner(),
                 chunk_offsets,
                 end_compressed_data,
                 uchunk_length: uchunk_length as usize,
-                ufile_length: uncompressed as u64 })
+                ufile_length: uncompressed as u64,
+                last_chunk: None })
     }
 
     fn get_chunks_for(&self, start_offset: u64, length: u64) -> Result<Vec<Chunk>, DictError> {
         let mut chunks = Vec::new();
         let start_chunk = start_offset as usize / self.uchunk_length;
-        let end_chunk = (start_offset + length) as usize / self.uchunk_length;
+        // chunk of the last byte; a definition ending at a chunk border doesn't need the next one
+        let end_chunk = (start_offset + length.max(1) - 1) as usize / self.uchunk_length;
         for id in start_chunk..=end_chunk {
             let chunk_length = match self.chunk_offsets.get(id+1) {
                 Some(next) => next - self.chunk_offsets[id],
                 None => self.end_compressed_data - self.chunk_offsets[id],
             };
-            chunks.push(Chunk { offset: self.chunk_offsets[id], length: chunk_length });
+            chunks.push(Chunk { id, offset: self.chunk_offsets[id], length: chunk_length });
         }
 
         Ok(chunks)
@@ -263,6 +293,10 @@ impl<B: Read + Seek> DictReaderDz<B> {
 impl<B: Read + Seek> DictReader for DictReaderDz<B> {
     // Fetch definition from the dictionary.
     fn fetch_definition(&mut self, start_offset: u64, length: u64) -> Result<String, DictError> {
+        Ok(String::from_utf8(self.fetch_bytes(start_offset, length)?)?)
+    }
+
+    fn fetch_bytes(&mut self, start_offset: u64, length: u64) -> Result<Vec<u8>, DictError> {
         if length > MAX_BYTES_FOR_BUFFER {
             return Err(DictError::MemoryError);
         }
@@ -270,38 +304,38 @@ impl<B: Read + Seek> DictReader for DictReaderDz<B> {
             return Err(DictError::IoError(io::Error::new(io::ErrorKind::UnexpectedEof, "a \
                       seek beyond the end of uncompressed data was requested")));
         }
-        let mut data = Vec::new();
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+        let end_offset = start_offset + length;
+        let mut data = Vec::with_capacity(length as usize);
         for chunk in self.get_chunks_for(start_offset, length)? {
-            let pos = self.dzdict.seek(SeekFrom::Start(chunk.offset as u64))?;
-            if pos != (chunk.offset as u64) {
-                return Err(DictError::IoError(io::Error::new(io::ErrorKind::Other, format!(
-                        "attempted to seek to {} but new position is {}",
-                        chunk.offset, pos))));
-            }
-            let mut definition = vec![0u8; chunk.length];
-            self.dzdict.read_exact(&mut definition)?;
-            data.push(self.inflate(definition)?);
-        };
-
-        // cut definition, convert to string
-        let cut_front = start_offset as usize % self.uchunk_length;
-        // join the chunks to one vector, only keeping the content of the definition
-        let data = match data.len() {
-            0 => panic!(),
-            1 => data[0][cut_front .. cut_front + length as usize].to_vec(),
-            n => {
-                let mut tmp = data[0][cut_front..].to_vec();
-                // first vec has been inserted into tmp, therefore skip first and last chunk, too
-                for text in data.iter().skip(1).take(n-2) {
-                    tmp.extend_from_slice(text);
-                }
-                // add last chunk to tmp, omitting stuff after word definition end
-                let remaining_bytes = (length as usize + cut_front) % self.uchunk_length;
-                tmp.extend_from_slice(&data[n-1][..remaining_bytes]);
-                tmp
-            },
-        };
-        Ok(String::from_utf8(data)?)
+            let inflated = match self.last_chunk {
+                Some((id, ref inflated)) if id == chunk.id => inflated,
+                _ => {
+                    let pos = self.dzdict.seek(SeekFrom::Start(chunk.offset as u64))?;
+                    if pos != (chunk.offset as u64) {
+                        return Err(DictError::IoError(io::Error::other(format!(
+                                "attempted to seek to {} but new position is {}",
+                                chunk.offset, pos))));
+                    }
+                    let mut definition = vec![0u8; chunk.length];
+                    self.dzdict.read_exact(&mut definition)?;
+                    let inflated = self.inflate(definition)?;
+                    &self.last_chunk.insert((chunk.id, inflated)).1
+                },
+            };
+            // only copy the part of the chunk belonging to the definition
+            let chunk_start = (chunk.id * self.uchunk_length) as u64;
+            let from = start_offset.saturating_sub(chunk_start) as usize;
+            let to = (end_offset - chunk_start).min(self.uchunk_length as u64) as usize;
+            data.extend_from_slice(&inflated[from..to]);
+        }
+        Ok(data)
+    }
+
+    fn total_length(&self) -> Option<u64> {
+        Some(self.ufile_length)
     }
 }
 
@@ -0,0 +1,95 @@
+//! Write .dict.dz files.
+//!
+//! A `.dict.dz` file is a gzip file (RFC 1952) whose content was compressed in chunks of a fixed
+//! size. After each chunk, the compressor is flushed completely, so that each chunk can be
+//! inflated on its own. The compressed length of each chunk is stored in the `RA` subfield of the
+//! FEXTRA header, see `man dictzip`. Files written here can be read with
+//! [DictReaderDz](../dictreader/struct.DictReaderDz.html), `dictzip` and `gzip`.
+
+use byteorder::{LittleEndian, WriteBytesExt};
+use flate2::{Compress, Compression, Crc, FlushCompress, Status};
+use std::io::{self, Write};
+
+use crate::dictreader::GZ_FEXTRA;
+use crate::errors::DictError;
+
+/// length of an uncompressed chunk, as chosen by dictzip; small enough that the compressed
+/// chunk length always fits into 16 bits
+pub const DICTZIP_CHUNK_LENGTH: usize = 58315;
+
+/// the chunk lengths are stored in the FEXTRA field, which has a 16-bit length itself
+const MAX_CHUNK_COUNT: usize = (u16::MAX as usize - 10) / 2;
+
+/// Compress `data` into the dictzip format and write it to `writer`.
+pub fn write_dictzip<W: Write>(data: &[u8], mut writer: W) -> Result<(), DictError> {
+    let mut chunks: Vec<&[u8]> = data.chunks(DICTZIP_CHUNK_LENGTH).collect();
+    if chunks.is_empty() { // even an empty file needs a chunk with the end of the stream
+        chunks.push(&[]);
+    }
+    if chunks.len() > MAX_CHUNK_COUNT {
+        return Err(DictError::InvalidFileFormat(format!("{} bytes are too many for a dictzip \
+                file", data.len()), None));
+    }
+
+    let mut compressor = Compress::new(Compression::best(), false);
+    let mut compressed_chunks = Vec::with_capacity(chunks.len());
+    for (number, chunk) in chunks.iter().enumerate() {
+        let flush = if number + 1 == chunks.len() {
+            FlushCompress::Finish
+        } else {
+            FlushCompress::Full
+        };
+        let compressed = compress_chunk(&mut compressor, chunk, flush)?;
+        if compressed.len() > u16::MAX as usize {
+            return Err(DictError::InvalidFileFormat(format!("chunk {} could not be compressed \
+                    to less than 64 KiB", number), None));
+        }
+        compressed_chunks.push(compressed);
+    }
+
+    // gzip header: magic, deflate, flags, mtime, maximum compression, Unix
+    writer.write_all(&[0x1F, 0x8B, 8, GZ_FEXTRA, 0, 0, 0, 0, 2, 3])?;
+    let subfield_length = 6 + 2 * compressed_chunks.len();
+    writer.write_u16::<LittleEndian>(subfield_length as u16 + 4)?; // XLEN
+    writer.write_all(b"RA")?;
+    writer.write_u16::<LittleEndian>(subfield_length as u16)?;
+    writer.write_u16::<LittleEndian>(1)?; // version
+    writer.write_u16::<LittleEndian>(DICTZIP_CHUNK_LENGTH as u16)?;
+    writer.write_u16::<LittleEndian>(compressed_chunks.len() as u16)?;
+    for chunk in &compressed_chunks {
+        writer.write_u16::<LittleEndian>(chunk.len() as u16)?;
+    }
+    for chunk in &compressed_chunks {
+        writer.write_all(chunk)?;
+    }
+
+    // trailer: CRC32 and length of the uncompressed data
+    let mut crc = Crc::new();
+    crc.update(data);
+    writer.write_u32::<LittleEndian>(crc.sum())?;
+    writer.write_u32::<LittleEndian>(data.len() as u32)?;
+    Ok(())
+}
+
+fn compress_chunk(compressor: &mut Compress, chunk: &[u8], flush: FlushCompress)
+        -> Result<Vec<u8>, DictError> {
+    let start = compressor.total_in();
+    let mut compressed = Vec::with_capacity(chunk.len() / 2 + 64);
+    loop {
+        if compressed.capacity() - compressed.len() < 64 {
+            compressed.reserve(chunk.len() / 4 + 64);
+        }
+        let consumed = (compressor.total_in() - start) as usize;
+        let status = compressor.compress_vec(&chunk[consumed..], &mut compressed, flush)
+            .map_err(io::Error::other)?;
+        let consumed = (compressor.total_in() - start) as usize;
+        // the flush is complete once the compressor stops filling the whole output buffer
+        let flushed = consumed == chunk.len() && compressed.len() < compressed.capacity();
+        match flush {
+            FlushCompress::Finish if status == Status::StreamEnd => return Ok(compressed),
+            FlushCompress::Finish => (),
+            _ if flushed => return Ok(compressed),
+            _ => (),
+        }
+    }
+}
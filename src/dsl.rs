@@ -0,0 +1,367 @@
+//! Read dictionaries in the ABBYY Lingvo DSL format.
+//!
+//! DSL is the source format of ABBYY Lingvo dictionaries. A DSL file starts with headers like
+//! `#NAME "English - German"` or `#INDEX_LANGUAGE "English"`, followed by cards. Each card has one
+//! or more headword lines, starting in the first column, and a body made up of the following
+//! indented lines:
+//!
+//! ```text
+//! #NAME "Latin - German"
+//! #INDEX_LANGUAGE "Latin"
+//! #CONTENTS_LANGUAGE "German"
+//!
+//! mater
+//! matr{is}
+//!     [m1][trn]Mutter[/trn][/m]
+//!     [m2][ex]~ familias[/ex][/m]
+//! ```
+//!
+//! Files are usually encoded in UTF-16LE, but UTF-8 (and UTF-16BE) are accepted as well; the
+//! encoding is detected from the byte order mark or, failing that, from the first character.
+//! Gzipped files (`.dsl.dz`) are decompressed transparently.
+//!
+//! Within headwords, parts in curly braces are unsorted: they are displayed, but not indexed.
+//! Parts in parentheses are optional, so `(to) go` is indexed both as `to go` and `go`; a
+//! headword with many optional parts is only indexed with all of them and without any. Bodies
+//! are rendered to plain text: the margin of `[mN]` becomes an indentation of N spaces (one if
+//! none is given), `~` is replaced by the headword, references (`[ref]`, `<<...>>`) are put in
+//! curly braces like in the FreeDict databases, media files (`[s]`) are dropped and all other
+//! tags, e.g. `[trn]`, `[b]` or `[c red]`, are removed. Comments (`{{...}}`) are ignored.
+//!
+//! The cards are written with a [DictionaryBuilder](../builder/struct.DictionaryBuilder.html).
+
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::builder::DictionaryBuilder;
+use crate::errors::DictError;
+use crate::metadata::DatabaseInfo;
+use crate::Dictionary;
+
+/// number of optional parts of a headword up to which all combinations are indexed
+const MAX_OPTIONAL_PARTS: usize = 4;
+
+/// A card of a DSL file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Card {
+    /// headwords as indexed, without unsorted parts and with optional parts expanded
+    pub headwords: Vec<String>,
+    /// headwords as displayed, with unsorted and optional parts
+    pub titles: Vec<String>,
+    /// lines of the body with their indentation removed, still containing the DSL markup
+    pub body: Vec<String>,
+}
+
+/// Decode the content of a DSL file.
+///
+/// UTF-16 is recognised by its byte order mark or by a NUL byte in the first character, which is
+/// usually the `#` of a header; everything else is taken to be UTF-8.
+pub fn decode(data: &[u8]) -> Result<String, DictError> {
+    let utf16 = |data: &[u8], little_endian: bool| -> Result<String, DictError> {
+        let units = data.chunks(2).map(|pair| match (pair, little_endian) {
+            ([low, high], true) | ([high, low], false) => u16::from_le_bytes([*low, *high]),
+            _ => 0xFFFD, // odd number of bytes
+        });
+        char::decode_utf16(units).collect::<Result<String, _>>().map_err(|e|
+                DictError::InvalidFileFormat(format!("invalid UTF-16 in DSL file: {}", e),
+                        None))
+    };
+    match data {
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, true),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, false),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => Ok(String::from_utf8(rest.to_vec())?),
+        [first, 0, ..] if *first != 0 => utf16(data, true),
+        [0, second, ..] if *second != 0 => utf16(data, false),
+        _ => Ok(String::from_utf8(data.to_vec())?),
+    }
+}
+
+/// Parse a DSL file into its metadata and cards.
+///
+/// The `#NAME` header becomes the short name, the languages are listed in the long description.
+pub fn parse_dsl<R: Read>(mut reader: R) -> Result<(DatabaseInfo, Vec<Card>), DictError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    if data.starts_with(&[0x1F, 0x8B]) {
+        let mut decompressed = Vec::new();
+        MultiGzDecoder::new(&data[..]).read_to_end(&mut decompressed)?;
+        data = decompressed;
+    }
+    let text = remove_comments(&decode(&data)?);
+
+    let mut info = DatabaseInfo { utf8: true, ..DatabaseInfo::default() };
+    let mut languages = Vec::new();
+    let mut cards: Vec<Card> = Vec::new();
+    let mut in_body = false;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with('#') && cards.is_empty() {
+            let (name, value) = parse_header(line);
+            match name {
+                "NAME" => info.short_name = Some(value),
+                "INDEX_LANGUAGE" => languages.push(format!("Index language: {}", value)),
+                "CONTENTS_LANGUAGE" => languages.push(format!("Contents language: {}", value)),
+                _ => (),
+            }
+        } else if line.trim().is_empty() {
+            continue;
+        } else if line.starts_with([' ', '\t']) {
+            match cards.last_mut() {
+                Some(card) => card.body.push(line.trim().to_string()),
+                None => return Err(DictError::InvalidFileFormat(format!("line {} of DSL file \
+                        has a body without headword", number + 1), None)),
+            }
+            in_body = true;
+        } else {
+            if in_body || cards.is_empty() {
+                cards.push(Card::default());
+                in_body = false;
+            }
+            let card = cards.last_mut().unwrap(); // pushed above if there was none
+            card.titles.push(render_markup(&remove_braces(line), ""));
+            for headword in index_forms(line) {
+                if !card.headwords.contains(&headword) {
+                    card.headwords.push(headword);
+                }
+            }
+        }
+    }
+    if !languages.is_empty() {
+        info.info = Some(languages.join("\n"));
+    }
+    Ok((info, cards))
+}
+
+/// Create a builder with the cards of a DSL file.
+///
+/// Cards without a body or without an indexable headword are skipped.
+pub fn convert<R: Read>(reader: R) -> Result<DictionaryBuilder, DictError> {
+    let (info, cards) = parse_dsl(reader)?;
+    let mut builder = DictionaryBuilder::new();
+    builder.set_info(info);
+    for card in cards.iter().filter(|c| !c.headwords.is_empty() && !c.body.is_empty()) {
+        let headwords: Vec<&str> = card.headwords.iter().map(|h| h.as_str()).collect();
+        builder.add_entry(&headwords, &render(card))?;
+    }
+    Ok(builder)
+}
+
+/// Convert a DSL file into a dict file and an index.
+///
+/// The dict file is compressed if its name ends on `.dz`.
+pub fn convert_file<P: AsRef<Path>>(dsl_path: P, dict_path: P, index_path: P)
+        -> Result<(), DictError> {
+    let builder = convert(BufReader::new(File::open(dsl_path)?))?;
+    builder.write_files(dict_path, index_path)
+}
+
+/// Load a DSL file as an in-memory dictionary.
+pub fn load_dsl<P: AsRef<Path>>(dsl_path: P) -> Result<Dictionary, DictError> {
+    convert(BufReader::new(File::open(dsl_path)?))?.build()
+}
+
+/// Render a card to plain text.
+///
+/// The first line holds the headwords, the body lines follow, indented by their margin.
+pub fn render(card: &Card) -> String {
+    let headword = card.headwords.first().map(|h| h.as_str()).unwrap_or("");
+    let mut text = card.titles.join(", ");
+    text.push('\n');
+    for line in &card.body {
+        let (margin, line) = split_margin(line);
+        let rendered = render_markup(line, headword);
+        text.push_str(&" ".repeat(margin));
+        text.push_str(rendered.trim());
+        text.push('\n');
+    }
+    text
+}
+
+/// Split a header line like `#NAME "Latin - German"` into name and value.
+fn parse_header(line: &str) -> (&str, String) {
+    let line = line.trim_start_matches('#');
+    let (name, value) = line.split_once([' ', '\t']).unwrap_or((line, ""));
+    (name, value.trim().trim_matches('"').to_string())
+}
+
+/// Remove `{{...}}` comments, which may span several lines.
+fn remove_comments(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        rest = match rest[start..].find("}}") {
+            Some(end) => &rest[start + end + 2..],
+            None => "",
+        };
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Remove the braces around unsorted parts, keeping their content and escaped braces.
+fn remove_braces(headword: &str) -> String {
+    let mut result = String::with_capacity(headword.len());
+    let mut chars = headword.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                result.push('\\');
+                result.extend(chars.next());
+            },
+            '{' | '}' => (),
+            _ => result.push(ch),
+        }
+    }
+    result
+}
+
+/// Get the forms of a headword to be indexed.
+///
+/// Unsorted parts are dropped, every optional part doubles the number of forms and escapes are
+/// resolved. Headwords with more than `MAX_OPTIONAL_PARTS` optional parts are only indexed with
+/// all of them and without any.
+fn index_forms(headword: &str) -> Vec<String> {
+    let mut forms = vec![String::new()];
+    let mut optional: Option<String> = None;
+    let mut optional_parts = 0;
+    let mut unsorted = false;
+    let mut chars = headword.chars();
+    while let Some(ch) = chars.next() {
+        let literal = match ch {
+            '\\' => match chars.next() {
+                Some(escaped) => escaped,
+                None => continue,
+            },
+            '{' => { unsorted = true; continue },
+            '}' => { unsorted = false; continue },
+            _ if unsorted => continue,
+            '(' if optional.is_none() => { optional = Some(String::new()); continue },
+            ')' if optional.is_some() => {
+                let part = optional.take().unwrap_or_default();
+                optional_parts += 1;
+                if optional_parts <= MAX_OPTIONAL_PARTS {
+                    let with_part: Vec<String> = forms.iter().map(|f| format!("{}{}", f, part))
+                        .collect();
+                    forms.extend(with_part);
+                } else {
+                    // the first form has none of the optional parts, the last one all of them
+                    let mut full = forms.pop().unwrap_or_default();
+                    full.push_str(&part);
+                    forms.truncate(1);
+                    forms.push(full);
+                }
+                continue;
+            },
+            _ => ch,
+        };
+        match optional {
+            Some(ref mut part) => part.push(literal),
+            None => forms.iter_mut().for_each(|f| f.push(literal)),
+        }
+    }
+    let mut result: Vec<String> = Vec::with_capacity(forms.len());
+    for form in forms {
+        let form = form.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !form.is_empty() && !result.contains(&form) {
+            result.push(form);
+        }
+    }
+    // the complete form comes first
+    result.reverse();
+    result
+}
+
+/// Split the margin from a body line starting with `[mN]`.
+fn split_margin(line: &str) -> (usize, &str) {
+    if let Some(rest) = line.strip_prefix("[m") {
+        if let Some(end) = rest.find(']') {
+            if let Ok(margin) = rest[..end].parse() {
+                return (margin, &rest[end + 1..]);
+            } else if end == 0 {
+                return (1, &rest[1..]);
+            }
+        }
+    }
+    (1, line)
+}
+
+/// Render DSL markup to plain text, replacing `~` by the given headword.
+fn render_markup(text: &str, headword: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut skip = false; // within a media file reference
+    let mut rest = text;
+    while let Some(ch) = rest.chars().next() {
+        rest = &rest[ch.len_utf8()..];
+        match ch {
+            '\\' => {
+                if let Some(escaped) = rest.chars().next() {
+                    rest = &rest[escaped.len_utf8()..];
+                    if !skip {
+                        result.push(escaped);
+                    }
+                }
+            },
+            '[' => match rest.find(']') {
+                Some(end) => {
+                    let tag = &rest[..end];
+                    rest = &rest[end + 1..];
+                    let name = tag.split_whitespace().next().unwrap_or("");
+                    match name {
+                        "s" => skip = true,
+                        "/s" => skip = false,
+                        "ref" if !skip => result.push('{'),
+                        "/ref" if !skip => result.push('}'),
+                        _ => (),
+                    }
+                },
+                None => result.push(ch),
+            },
+            '<' if rest.starts_with('<') => {
+                rest = &rest[1..];
+                result.push('{');
+            },
+            '>' if rest.starts_with('>') => {
+                rest = &rest[1..];
+                result.push('}');
+            },
+            _ if skip => (),
+            '~' => result.push_str(headword),
+            _ => result.push(ch),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optional_and_unsorted_parts_of_headwords() {
+        assert_eq!(index_forms("(to) go{ away}"), vec!["to go", "go"]);
+        assert_eq!(index_forms("matr{is}"), vec!["matr"]);
+        assert_eq!(index_forms("a\\(b\\)"), vec!["a(b)"]);
+        assert_eq!(render_markup(&remove_braces("(to) go{ away}"), ""), "(to) go away");
+    }
+
+    #[test]
+    fn test_many_optional_parts_are_not_combined() {
+        assert_eq!(index_forms("a(b)(c)(d)(e)").len(), 16);
+        let headword = "a(b)(c)(d)(e)(f)".repeat(10);
+        assert_eq!(index_forms(&headword), vec!["abcdef".repeat(10), "a".repeat(10)]);
+    }
+
+    #[test]
+    fn test_markup_is_removed() {
+        assert_eq!(render_markup("[trn][c red]Rose[/c][/trn] [s]rose.wav[/s]", "rosa"),
+                "Rose ");
+        assert_eq!(render_markup("[ex]~ rubra[/ex], [ref]lilium[/ref], <<flos>>", "rosa"),
+                "rosa rubra, {lilium}, {flos}");
+        assert_eq!(render_markup("\\[sic\\] \\~", ""), "[sic] ~");
+        assert_eq!(split_margin("[m2]text[/m]"), (2, "text[/m]"));
+        assert_eq!(split_margin("text"), (1, "text"));
+    }
+}
@@ -12,6 +12,9 @@ pub enum DictError {
     InvalidCharacter(char, Option<usize>, Option<usize>),
     /// Occurs whenever a line in an index file misses a column.
     MissingColumnInIndex(usize),
+    /// A line of an index file could not be parsed; contains the line number (starting at 1), the
+    /// raw line and the error which occurred.
+    InvalidIndexLine(usize, String, Box<DictError>),
     /// Invalid file format, contains an explanation an optional path to the
     /// file with the invalid file format.
     InvalidFileFormat(String, Option<String>),
@@ -19,6 +22,16 @@ pub enum DictError {
     MemoryError,
     /// This reports words which are not present in the dictionary.
     WordNotFound(String),
+    /// A search pattern (regular expression, glob) could not be compiled; contains an explanation.
+    InvalidPattern(String),
+    /// A match strategy with the given name is not known.
+    UnknownStrategy(String),
+    /// A database with the given name is not part of the collection.
+    UnknownDatabase(String),
+    /// A database of the collection could not be opened before; contains its name and the reason.
+    UnavailableDatabase(String, String),
+    /// A search took longer than the configured time limit.
+    Timeout,
     /// A wrapped io::Error.
     IoError(::std::io::Error),
     /// A wrapped Utf8Error.
@@ -36,6 +49,14 @@ impl ::std::fmt::Display for DictError {
                         the flate2 crate: {:?}", err),
             DictError::MemoryError => write!(f, "not enough memory available"),
             DictError::WordNotFound(ref word) => write!(f, "Word not found: {}", word),
+            DictError::InvalidPattern(ref explanation) => write!(f, "Invalid search \
+                        pattern: {}", explanation),
+            DictError::UnknownStrategy(ref name) => write!(f, "Unknown match strategy: {}",
+                        name),
+            DictError::UnknownDatabase(ref name) => write!(f, "Unknown database: {}", name),
+            DictError::UnavailableDatabase(ref name, ref reason) => write!(f, "Database {} \
+                        could not be opened: {}", name, reason),
+            DictError::Timeout => write!(f, "search exceeded its time limit"),
             DictError::InvalidCharacter(ref ch, ref line, ref pos) => {
                 let mut ret = write!(f, "Invalid character {}", ch);
                 if let Some(ln) = *line {
@@ -48,6 +69,8 @@ impl ::std::fmt::Display for DictError {
             },
             DictError::MissingColumnInIndex(ref lnum) => write!(f, "line {}: not \
                     enough <tab>-separated columns found, expected 3", lnum),
+            DictError::InvalidIndexLine(ref lnum, ref line, ref err) => write!(f, "line {} \
+                    of index ({:?}): {}", lnum, line, err),
             DictError::InvalidFileFormat(ref explanation, ref path) =>
                 write!(f, "{}{}", path.clone().unwrap_or_else(String::new), explanation)
         }
@@ -60,13 +83,19 @@ impl error::Error for DictError {
             DictError::InvalidCharacter(_, _, _) => "invalid character",
             DictError::MemoryError => "not enough memory available",
             DictError::WordNotFound(_) => "word not found",
+            DictError::InvalidPattern(_) => "invalid search pattern",
+            DictError::UnknownStrategy(_) => "unknown match strategy",
+            DictError::UnknownDatabase(_) => "unknown database",
+            DictError::UnavailableDatabase(_, _) => "database could not be opened",
+            DictError::Timeout => "search exceeded its time limit",
             DictError::MissingColumnInIndex(_) =>
                     "not enough <tab>-separated columns given",
+            DictError::InvalidIndexLine(_, _, _) => "invalid line in index",
             DictError::InvalidFileFormat(ref _explanation, ref _path) => "could not \
                     determine file format",
-            DictError::IoError(ref err) => err.description(),
+            DictError::IoError(_) => "I/O error",
             DictError::DeflateError(_) => "invalid data, couldn't inflate",
-            DictError::Utf8Error(ref err) => err.description(),
+            DictError::Utf8Error(_) => "invalid UTF-8 sequence",
         }
     }
 
@@ -74,11 +103,48 @@ impl error::Error for DictError {
         match *self {
             DictError::IoError(ref err) => err.source(),
             DictError::Utf8Error(ref err) => err.source(),
+            DictError::InvalidIndexLine(_, _, ref err) => Some(err.as_ref()),
             _ => None,
         }
     }
 }
 
+impl DictError {
+    /// Name of the variant, e.g. `WordNotFound`.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            DictError::InvalidCharacter(_, _, _) => "InvalidCharacter",
+            DictError::MissingColumnInIndex(_) => "MissingColumnInIndex",
+            DictError::InvalidIndexLine(_, _, _) => "InvalidIndexLine",
+            DictError::InvalidFileFormat(_, _) => "InvalidFileFormat",
+            DictError::MemoryError => "MemoryError",
+            DictError::WordNotFound(_) => "WordNotFound",
+            DictError::InvalidPattern(_) => "InvalidPattern",
+            DictError::UnknownStrategy(_) => "UnknownStrategy",
+            DictError::UnknownDatabase(_) => "UnknownDatabase",
+            DictError::UnavailableDatabase(_, _) => "UnavailableDatabase",
+            DictError::Timeout => "Timeout",
+            DictError::IoError(_) => "IoError",
+            DictError::Utf8Error(_) => "Utf8Error",
+            DictError::DeflateError(_) => "DeflateError",
+        }
+    }
+}
+
+/// Errors are serialised as a struct with the `kind` of the error and its `message`.
+///
+/// Wrapped errors of other crates cannot be restored, so errors can only be serialised.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DictError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut error = serializer.serialize_struct("DictError", 2)?;
+        error.serialize_field("kind", self.kind())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
 // allow seamless coercion from::Error
 impl From<::std::io::Error> for DictError {
     fn from(err: ::std::io::Error) -> DictError {
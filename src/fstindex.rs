@@ -0,0 +1,340 @@
+//! Compact index backend based on a finite-state transducer.
+//!
+//! An [Index](../indexing/type.Index.html) keeps every headword in its own heap allocation, which
+//! adds up when many dictionaries are loaded at once. The [FstIndex](struct.FstIndex.html) stores
+//! all headwords in a single [finite-state transducer](https://docs.rs/fst), which shares common
+//! prefixes and suffixes of the headwords, and maps each headword to its offset and length packed
+//! into a single number. Headwords with several locations or with an original spelling are kept
+//! in a separate table. A [Dictionary](../struct.Dictionary.html) keeps its index in an FST
+//! index after calling [compact_index](../struct.Dictionary.html#method.compact_index).
+//!
+//! Apart from exact lookups, the transducer can answer prefix, range and fuzzy (Levenshtein)
+//! queries without looking at every headword. This module is only available with the `fst`
+//! feature.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use fst::automaton::{Automaton, Str};
+use fst::{IntoStreamer, Map, Streamer};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::ops::Bound;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::errors::DictError;
+use crate::indexing::{self, Index, Location};
+use crate::matching::{MatchOptions, TIMEOUT_CHECK_INTERVAL};
+
+/// identification of a persisted FST index
+const MAGIC: &[u8; 4] = b"DFST";
+/// version of the file format
+const FORMAT_VERSION: u32 = 1;
+
+/// bit marking a value as a reference into the table of locations which could not be packed
+const OVERFLOW_FLAG: u64 = 1 << 63;
+/// number of bits used for the length of a packed location
+const LENGTH_BITS: u32 = 24;
+/// the offset of a packed location must fit into the bits not used for the length and the flag
+const MAX_PACKED_OFFSET: u64 = (1 << (63 - LENGTH_BITS)) - 1;
+
+/// Index mapping headwords to locations within a dict file using a finite-state transducer.
+pub struct FstIndex {
+    map: Map<Vec<u8>>,
+    /// locations which could not be packed into the value of the transducer
+    overflow: Vec<Vec<Location>>,
+}
+
+impl FstIndex {
+    /// Build the FST index from an index, e.g. as returned by
+    /// [parse_index](../indexing/fn.parse_index.html).
+    pub fn from_index(index: &Index) -> Result<FstIndex, DictError> {
+        let mut headwords: Vec<&String> = index.keys().collect();
+        headwords.sort_unstable();
+
+        let mut overflow = Vec::new();
+        let mut entries = Vec::with_capacity(headwords.len());
+        for headword in headwords {
+            let locations = &index[headword];
+            let value = match pack(locations) {
+                Some(value) => value,
+                None => {
+                    overflow.push(locations.clone());
+                    OVERFLOW_FLAG | (overflow.len() as u64 - 1)
+                },
+            };
+            entries.push((headword.as_bytes(), value));
+        }
+        let map = Map::from_iter(entries).map_err(fst_error)?;
+        Ok(FstIndex { map, overflow })
+    }
+
+    /// Number of headwords in the index.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Check whether the index contains no headwords at all.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Check whether the given headword is contained.
+    pub fn contains(&self, headword: &str) -> bool {
+        self.map.contains_key(headword)
+    }
+
+    /// Get all locations of a headword.
+    pub fn get(&self, headword: &str) -> Option<Vec<Location>> {
+        self.map.get(headword).map(|value| self.unpack(value))
+    }
+
+    /// Find all headwords starting with `prefix`, in sorted order.
+    pub fn prefix(&self, prefix: &str) -> Vec<String> {
+        collect_keys(self.map.search(Str::new(prefix).starts_with()).into_stream())
+    }
+
+    /// Find headwords starting with `prefix`, in sorted order.
+    ///
+    /// In contrast to [prefix](#method.prefix), the search stops after `options.limit` headwords
+    /// and fails with `DictError::Timeout` if it takes longer than `options.timeout`.
+    pub fn prefix_matches(&self, prefix: &str, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        collect_matches(self.map.search(Str::new(prefix).starts_with()).into_stream(), options)
+    }
+
+    /// Find all headwords within the given bounds, in sorted order.
+    pub fn range(&self, start: Bound<&str>, end: Bound<&str>) -> Vec<String> {
+        let mut builder = self.map.range();
+        builder = match start {
+            Bound::Included(s) => builder.ge(s),
+            Bound::Excluded(s) => builder.gt(s),
+            Bound::Unbounded => builder,
+        };
+        builder = match end {
+            Bound::Included(e) => builder.le(e),
+            Bound::Excluded(e) => builder.lt(e),
+            Bound::Unbounded => builder,
+        };
+        collect_keys(builder.into_stream())
+    }
+
+    /// Find all headwords within the given Levenshtein distance of `query`, in sorted order.
+    ///
+    /// The distance is computed on characters, not bytes.
+    pub fn fuzzy(&self, query: &str, max_distance: usize) -> Vec<String> {
+        let automaton = Levenshtein { query: query.chars().collect(), max_distance };
+        collect_keys(self.map.search(automaton).into_stream())
+    }
+
+    /// Find headwords within the given Levenshtein distance of `query`, in sorted order.
+    ///
+    /// The search is bounded like the one of [prefix_matches](#method.prefix_matches).
+    pub fn fuzzy_matches(&self, query: &str, max_distance: usize, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        let automaton = Levenshtein { query: query.chars().collect(), max_distance };
+        collect_matches(self.map.search(automaton).into_stream(), options)
+    }
+
+    /// All headwords in sorted order.
+    pub fn keys(&self) -> Vec<String> {
+        collect_keys(self.map.stream())
+    }
+
+    /// All headwords with their locations, sorted by headword.
+    ///
+    /// Unlike [to_index](#method.to_index), this does not build a hash map.
+    pub fn entries(&self) -> Vec<(String, Location)> {
+        let mut entries = Vec::with_capacity(self.len());
+        let mut stream = self.map.stream();
+        while let Some((key, value)) = stream.next() {
+            let key = String::from_utf8_lossy(key).into_owned();
+            for location in self.unpack(value) {
+                entries.push((key.clone(), location));
+            }
+        }
+        entries
+    }
+
+    /// Convert back into a plain index.
+    pub fn to_index(&self) -> Index {
+        let mut index = Index::with_capacity(self.len());
+        let mut stream = self.map.stream();
+        while let Some((key, value)) = stream.next() {
+            index.insert(String::from_utf8_lossy(key).into_owned(), self.unpack(value));
+        }
+        index
+    }
+
+    /// Write the index to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), DictError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load an index previously written with [save](#method.save).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<FstIndex, DictError> {
+        FstIndex::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Serialise the index.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), DictError> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<LittleEndian>(FORMAT_VERSION)?;
+        let fst = self.map.as_fst().as_bytes();
+        writer.write_u64::<LittleEndian>(fst.len() as u64)?;
+        writer.write_all(fst)?;
+        writer.write_u64::<LittleEndian>(self.overflow.len() as u64)?;
+        for locations in &self.overflow {
+            indexing::write_locations(&mut writer, locations)?;
+        }
+        Ok(())
+    }
+
+    /// Deserialise an index written with [write_to](#method.write_to).
+    pub fn read_from<R: Read>(mut reader: R) -> Result<FstIndex, DictError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(DictError::InvalidFileFormat("not an FST index".into(), None));
+        }
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version != FORMAT_VERSION {
+            return Err(DictError::InvalidFileFormat(format!("unsupported FST index \
+                    version {}", version), None));
+        }
+        let fst_length = reader.read_u64::<LittleEndian>()?;
+        let mut fst = Vec::new();
+        reader.by_ref().take(fst_length).read_to_end(&mut fst)?;
+        if fst.len() as u64 != fst_length {
+            return Err(DictError::IoError(io::Error::new(io::ErrorKind::UnexpectedEof,
+                    "FST index is truncated")));
+        }
+        let map = Map::new(fst).map_err(fst_error)?;
+
+        let overflow_count = reader.read_u64::<LittleEndian>()?;
+        let mut overflow = Vec::new();
+        for _ in 0..overflow_count {
+            overflow.push(indexing::read_locations(&mut reader)?);
+        }
+        // references into the overflow table are used without further checks
+        let mut stream = map.stream();
+        while let Some((key, value)) = stream.next() {
+            if value & OVERFLOW_FLAG != 0 && (value & !OVERFLOW_FLAG) >= overflow.len() as u64 {
+                return Err(DictError::InvalidFileFormat(format!("FST index refers to missing \
+                        locations for {:?}", String::from_utf8_lossy(key)), None));
+            }
+        }
+        drop(stream);
+        Ok(FstIndex { map, overflow })
+    }
+
+    fn unpack(&self, value: u64) -> Vec<Location> {
+        if value & OVERFLOW_FLAG != 0 {
+            self.overflow[(value & !OVERFLOW_FLAG) as usize].clone()
+        } else {
+            vec![Location::new(value >> LENGTH_BITS, value & ((1 << LENGTH_BITS) - 1))]
+        }
+    }
+}
+
+/// Pack a single location without original spelling into one number.
+fn pack(locations: &[Location]) -> Option<u64> {
+    match locations {
+        [Location { offset, length, original: None }]
+            if *offset <= MAX_PACKED_OFFSET && *length < (1 << LENGTH_BITS) =>
+                Some((offset << LENGTH_BITS) | length),
+        _ => None,
+    }
+}
+
+fn collect_keys<S: for<'a> Streamer<'a, Item=(&'a [u8], u64)>>(mut stream: S) -> Vec<String> {
+    let mut keys = Vec::new();
+    while let Some((key, _)) = stream.next() {
+        keys.push(String::from_utf8_lossy(key).into_owned());
+    }
+    keys
+}
+
+/// Collect the keys of a stream, bounded by the limit and the time limit of `options`.
+fn collect_matches<S>(mut stream: S, options: &MatchOptions) -> Result<Vec<String>, DictError>
+        where S: for<'a> Streamer<'a, Item=(&'a [u8], u64)> {
+    let start = Instant::now();
+    let mut keys = Vec::new();
+    let mut count = 0;
+    while keys.len() < options.limit {
+        if count % TIMEOUT_CHECK_INTERVAL == 0 && start.elapsed() > options.timeout {
+            return Err(DictError::Timeout);
+        }
+        count += 1;
+        match stream.next() {
+            Some((key, _)) => keys.push(String::from_utf8_lossy(key).into_owned()),
+            None => break,
+        }
+    }
+    Ok(keys)
+}
+
+fn fst_error(err: fst::Error) -> DictError {
+    DictError::InvalidFileFormat(format!("invalid FST index: {}", err), None)
+}
+
+/// Automaton accepting all words within a Levenshtein distance of the query.
+///
+/// The state is the last row of the dynamic programming matrix of the Levenshtein distance. The
+/// transducer passes bytes, so bytes of multi-byte characters are buffered until the character is
+/// complete.
+struct Levenshtein {
+    query: Vec<char>,
+    max_distance: usize,
+}
+
+#[derive(Clone)]
+struct LevenshteinState {
+    row: Vec<usize>,
+    /// bytes of an incomplete UTF-8 sequence
+    partial: Vec<u8>,
+}
+
+impl Automaton for Levenshtein {
+    type State = Option<LevenshteinState>;
+
+    fn start(&self) -> Self::State {
+        Some(LevenshteinState { row: (0..=self.query.len()).collect(), partial: Vec::new() })
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        state.as_ref().is_some_and(|s|
+                s.partial.is_empty() && s.row[self.query.len()] <= self.max_distance)
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        state.is_some()
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        let state = state.as_ref()?;
+        let mut partial = state.partial.clone();
+        partial.push(byte);
+        let ch = match std::str::from_utf8(&partial) {
+            Ok(s) => s.chars().next()?,
+            Err(e) if e.error_len().is_none() => { // incomplete sequence, wait for more bytes
+                return Some(LevenshteinState { row: state.row.clone(), partial });
+            },
+            Err(_) => return None,
+        };
+
+        let mut row = Vec::with_capacity(state.row.len());
+        row.push(state.row[0] + 1);
+        for (i, query_char) in self.query.iter().enumerate() {
+            let substitution = state.row[i] + if *query_char == ch { 0 } else { 1 };
+            row.push(substitution.min(state.row[i + 1] + 1).min(row[i] + 1));
+        }
+        // no continuation can get below the minimum of the row
+        if row.iter().min().is_some_and(|min| *min > self.max_distance) {
+            return None;
+        }
+        Some(LevenshteinState { row, partial: Vec::new() })
+    }
+}
@@ -0,0 +1,312 @@
+//! Full-text search over definitions.
+//!
+//! Headword lookups only help if the word in the source language is known. To find e.g. all Latin
+//! words which translate to "Rost", the definitions themselves have to be searched. The
+//! [DefinitionIndex](struct.DefinitionIndex.html) is an inverted index, mapping each term of the
+//! definitions to the definitions containing it. It is built by reading every definition once
+//! through a [DictReader](../dictreader/trait.DictReader.html) and can be saved, so that it
+//! doesn't need to be rebuilt on every start.
+//!
+//! A saved index may outlive the dictionary it was built for. Each index therefore records a
+//! fingerprint of the headwords, their spans and the length of the dict data, see
+//! [matches](struct.DefinitionIndex.html#method.matches). A
+//! [Dictionary](../struct.Dictionary.html) refuses an index with a different fingerprint.
+//!
+//! Definitions are split into terms at every character which is neither alphabetic nor numeric
+//! and terms are lowercased. Results are ranked with [Okapi
+//! BM25](https://en.wikipedia.org/wiki/Okapi_BM25).
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::cache::{fnv1a_extend, FNV1A_OFFSET_BASIS};
+use crate::dictreader::DictReader;
+use crate::errors::DictError;
+use crate::indexing::{self, Index, Location};
+use crate::validation::is_meta_headword;
+
+/// identification of a persisted definition index
+const MAGIC: &[u8; 4] = b"DFTI";
+/// version of the file format
+const FORMAT_VERSION: u32 = 2;
+
+/// BM25 parameter controlling the saturation of the term frequency
+const BM25_K1: f64 = 1.2;
+/// BM25 parameter controlling the normalisation by definition length
+const BM25_B: f64 = 0.75;
+
+/// A definition found by a full-text search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    /// all headwords pointing to the definition, in their original spelling
+    pub headwords: Vec<String>,
+    /// position of the definition within the dict file
+    pub location: Location,
+    /// relevance of the definition; higher is better
+    pub score: f64,
+}
+
+/// A definition of the inverted index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Document {
+    headwords: Vec<String>,
+    offset: u64,
+    length: u64,
+    /// number of terms in the definition
+    term_count: u32,
+}
+
+/// Inverted index over all definitions of a dictionary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DefinitionIndex {
+    documents: Vec<Document>,
+    /// term &rarr; (number of document, occurrences of the term in the document)
+    postings: HashMap<String, Vec<(u32, u32)>>,
+    /// fingerprint of the index and dict data the index was built from
+    fingerprint: u64,
+}
+
+/// Compute the fingerprint of a dictionary, see
+/// [DefinitionIndex::matches](struct.DefinitionIndex.html#method.matches).
+pub fn fingerprint(reader: &dyn DictReader, index: &Index) -> u64 {
+    fingerprint_entries(reader, &indexing::entries(index))
+}
+
+/// Compute the fingerprint from the locations of all (normalised) headwords.
+pub(crate) fn fingerprint_entries(reader: &dyn DictReader, entries: &[(&str, &Location)]) -> u64 {
+    let mut entries: Vec<(u64, u64, &str)> = entries.iter()
+        .filter(|(key, _)| !is_meta_headword(key))
+        .map(|(key, l)| (l.offset, l.length, l.original.as_deref().unwrap_or(key)))
+        .collect();
+    entries.sort_unstable();
+    let mut hash = fnv1a_extend(FNV1A_OFFSET_BASIS,
+            &reader.total_length().unwrap_or(u64::MAX).to_le_bytes());
+    for (offset, length, headword) in entries {
+        hash = fnv1a_extend(hash, &offset.to_le_bytes());
+        hash = fnv1a_extend(hash, &length.to_le_bytes());
+        hash = fnv1a_extend(hash, headword.as_bytes());
+        hash = fnv1a_extend(hash, &[0]);
+    }
+    hash
+}
+
+/// Split a text into lowercased terms.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.nfc().collect::<String>()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+impl DefinitionIndex {
+    /// Build the index by reading all definitions referenced by `index`.
+    ///
+    /// Headwords sharing a definition are grouped together. Meta entries like
+    /// `00-database-info` are skipped. Definitions are read in the order of the dict file, so that
+    /// compressed chunks are not inflated repeatedly.
+    pub fn build(reader: &mut dyn DictReader, index: &Index)
+            -> Result<DefinitionIndex, DictError> {
+        DefinitionIndex::build_from_entries(reader, &indexing::entries(index))
+    }
+
+    /// Build the index from the locations of all (normalised) headwords.
+    pub(crate) fn build_from_entries(reader: &mut dyn DictReader, entries: &[(&str, &Location)])
+            -> Result<DefinitionIndex, DictError> {
+        let mut spans: HashMap<(u64, u64), Vec<String>> = HashMap::new();
+        for (key, location) in entries.iter().filter(|(key, _)| !is_meta_headword(key)) {
+            let headword = location.original.as_deref().unwrap_or(key);
+            spans.entry((location.offset, location.length)).or_default()
+                .push(headword.to_string());
+        }
+        let mut spans: Vec<_> = spans.into_iter().collect();
+        spans.sort_unstable_by_key(|(span, _)| *span);
+
+        let mut documents = Vec::with_capacity(spans.len());
+        let mut postings: HashMap<String, Vec<(u32, u32)>> = HashMap::new();
+        for ((offset, length), mut headwords) in spans {
+            let definition = reader.fetch_definition(offset, length)?;
+            let terms = tokenize(&definition);
+            let mut frequencies: HashMap<String, u32> = HashMap::new();
+            for term in &terms {
+                *frequencies.entry(term.clone()).or_default() += 1;
+            }
+            let number = documents.len() as u32;
+            for (term, frequency) in frequencies {
+                postings.entry(term).or_default().push((number, frequency));
+            }
+            headwords.sort();
+            headwords.dedup();
+            documents.push(Document { headwords, offset, length,
+                    term_count: terms.len() as u32 });
+        }
+        for documents in postings.values_mut() {
+            documents.sort_unstable();
+        }
+        Ok(DefinitionIndex { documents, postings,
+                fingerprint: fingerprint_entries(reader, entries) })
+    }
+
+    /// Check whether the index was built from the given dictionary.
+    ///
+    /// This compares the [fingerprint](fn.fingerprint.html) of the headwords, their spans and the
+    /// length of the dict data with the one recorded when building the index. Changes of a
+    /// definition which keep all spans and the length of the data are not detected.
+    pub fn matches(&self, reader: &dyn DictReader, index: &Index) -> bool {
+        self.matches_entries(reader, &indexing::entries(index))
+    }
+
+    /// Check the fingerprint against the locations of all (normalised) headwords.
+    pub(crate) fn matches_entries(&self, reader: &dyn DictReader, entries: &[(&str, &Location)])
+            -> bool {
+        self.fingerprint == fingerprint_entries(reader, entries)
+    }
+
+    /// Number of indexed definitions.
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Check whether no definitions were indexed.
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Search for definitions containing any of the terms of `query`.
+    ///
+    /// Hits are ordered by descending score; definitions containing more of the terms, or rarer
+    /// terms, rank higher. Hits with the same score are ordered by their position in the dict
+    /// file.
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+        let count = self.documents.len() as f64;
+        let average_length = self.documents.iter().map(|d| f64::from(d.term_count)).sum::<f64>()
+            / count.max(1.0);
+
+        let mut scores: HashMap<u32, f64> = HashMap::new();
+        for postings in terms.iter().filter_map(|term| self.postings.get(term)) {
+            let frequency = postings.len() as f64;
+            let idf = ((count - frequency + 0.5) / (frequency + 0.5) + 1.0).ln();
+            for &(number, occurrences) in postings {
+                let occurrences = f64::from(occurrences);
+                let length = f64::from(self.documents[number as usize].term_count);
+                let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * length / average_length.max(1.0));
+                *scores.entry(number).or_default() +=
+                    idf * occurrences * (BM25_K1 + 1.0) / (occurrences + norm);
+            }
+        }
+
+        let mut scores: Vec<(u32, f64)> = scores.into_iter().collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        scores.into_iter().map(|(number, score)| {
+            let document = &self.documents[number as usize];
+            SearchHit { headwords: document.headwords.clone(),
+                location: Location::new(document.offset, document.length), score }
+        }).collect()
+    }
+
+    /// Write the index to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), DictError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load an index previously written with [save](#method.save).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<DefinitionIndex, DictError> {
+        DefinitionIndex::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Serialise the index.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), DictError> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<LittleEndian>(FORMAT_VERSION)?;
+        writer.write_u64::<LittleEndian>(self.fingerprint)?;
+        writer.write_u64::<LittleEndian>(self.documents.len() as u64)?;
+        for document in &self.documents {
+            writer.write_u32::<LittleEndian>(document.headwords.len() as u32)?;
+            for headword in &document.headwords {
+                indexing::write_string(&mut writer, headword)?;
+            }
+            writer.write_u64::<LittleEndian>(document.offset)?;
+            writer.write_u64::<LittleEndian>(document.length)?;
+            writer.write_u32::<LittleEndian>(document.term_count)?;
+        }
+        writer.write_u64::<LittleEndian>(self.postings.len() as u64)?;
+        for (term, postings) in &self.postings {
+            indexing::write_string(&mut writer, term)?;
+            writer.write_u32::<LittleEndian>(postings.len() as u32)?;
+            for &(number, occurrences) in postings {
+                writer.write_u32::<LittleEndian>(number)?;
+                writer.write_u32::<LittleEndian>(occurrences)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deserialise an index written with [write_to](#method.write_to).
+    pub fn read_from<R: Read>(mut reader: R) -> Result<DefinitionIndex, DictError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(DictError::InvalidFileFormat("not a definition index".into(), None));
+        }
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version != FORMAT_VERSION {
+            return Err(DictError::InvalidFileFormat(format!("unsupported definition index \
+                    version {}", version), None));
+        }
+        let fingerprint = reader.read_u64::<LittleEndian>()?;
+
+        let document_count = reader.read_u64::<LittleEndian>()?;
+        let mut documents = Vec::with_capacity(document_count.min(1 << 20) as usize);
+        for _ in 0..document_count {
+            let headword_count = reader.read_u32::<LittleEndian>()?;
+            let mut headwords = Vec::with_capacity(headword_count.min(1024) as usize);
+            for _ in 0..headword_count {
+                headwords.push(indexing::read_string(&mut reader)?);
+            }
+            let offset = reader.read_u64::<LittleEndian>()?;
+            let length = reader.read_u64::<LittleEndian>()?;
+            let term_count = reader.read_u32::<LittleEndian>()?;
+            documents.push(Document { headwords, offset, length, term_count });
+        }
+
+        let term_count = reader.read_u64::<LittleEndian>()?;
+        let mut postings = HashMap::with_capacity(term_count.min(1 << 20) as usize);
+        for _ in 0..term_count {
+            let term = indexing::read_string(&mut reader)?;
+            let count = reader.read_u32::<LittleEndian>()?;
+            let mut entries = Vec::with_capacity(count.min(1 << 20) as usize);
+            for _ in 0..count {
+                let number = reader.read_u32::<LittleEndian>()?;
+                if number as usize >= documents.len() {
+                    return Err(DictError::InvalidFileFormat(format!("definition index refers \
+                            to unknown definition {}", number), None));
+                }
+                entries.push((number, reader.read_u32::<LittleEndian>()?));
+            }
+            postings.insert(term, entries);
+        }
+        Ok(DefinitionIndex { documents, postings, fingerprint })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_is_split_into_lowercase_terms() {
+        assert_eq!(tokenize("abdo (abdere) <sg, v>\n 1. Verbergen"),
+                   vec!["abdo", "abdere", "sg", "v", "1", "verbergen"]);
+        assert_eq!(tokenize("zurück-ziehen"), vec!["zurück", "ziehen"]);
+    }
+}
@@ -16,16 +16,53 @@
 //! -   `x` is the index within the array given above, i.e. `'a' == 26`.
 //!
 //! The sum makes up the index.
+//!
+//! `dictfmt --index-keep-orig` writes an optional fourth column with the original spelling of the
+//! headword, e.g. "Rosa" for the normalised key "rosa". It is kept in the `original` field of the
+//! [Location](struct.Location.html).
+//!
+//! [parse_index](fn.parse_index.html) fails on the first invalid line. To inspect an index line by
+//! line, use [IndexEntries](struct.IndexEntries.html); to load an index despite invalid lines, use
+//! [parse_index_lenient](fn.parse_index_lenient.html).
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::path::Path;
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::fs::File;
 
 use crate::errors::DictError;
 use crate::errors::DictError::*;
 
+/// Position of a definition within a dict file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Location {
+    /// offset of the definition in bytes
+    pub offset: u64,
+    /// length of the definition in bytes
+    pub length: u64,
+    /// original spelling of the headword, if it differs from the key in the index
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub original: Option<String>,
+}
+
+impl Location {
+    /// Create a location for a headword without a separate original spelling.
+    pub fn new(offset: u64, length: u64) -> Location {
+        Location { offset, length, original: None }
+    }
+}
+
 /// Datastructure to hold the word &rarr; (position, length) information.
-pub type Index = HashMap<String, (u64, u64)>;
+///
+/// A headword may occur several times within an index, e.g. for homographs. All locations are
+/// kept, in the order of the index file. When headwords are merged by
+/// [normalize_index](fn.normalize_index.html), the merged locations are ordered by offset.
+pub type Index = HashMap<String, Vec<Location>>;
+
+/// digits of the index number encoding, in ascending order
+const BASE64_DIGITS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
 
 /// Get the assigned number for a character
 /// If the character was unknown, an empty Err(()) is returned.
@@ -44,7 +81,8 @@ fn get_base(input: char) -> Result<u64, ()> {
 /// Decode a number from a given String.
 ///
 /// This function decodes a number from the format described in the module documentation. If
-/// unknown characters/bytes are encountered, a `DictError` is returned.
+/// unknown characters/bytes are encountered or the number doesn't fit into 64 bits, a
+/// `DictError` is returned.
 ///
 /// # Example
 ///
@@ -60,15 +98,47 @@ fn get_base(input: char) -> Result<u64, ()> {
 pub fn decode_number(word: &str) -> Result<u64, DictError> {
     let mut index = 0u64;
     for (i, character) in word.chars().rev().enumerate() {
-        index += match get_base(character) {
-            Ok(x) => x * 64u64.pow(i as u32),
+        let digit = match get_base(character) {
+            Ok(x) => x,
             Err(_) => return Err(InvalidCharacter(character, None, Some(i))),
         };
+        if digit == 0 {
+            continue;
+        }
+        index = 64u64.checked_pow(i as u32)
+            .and_then(|weight| digit.checked_mul(weight))
+            .and_then(|value| index.checked_add(value))
+            .ok_or_else(|| DictError::InvalidFileFormat(format!("number {:?} is too large",
+                    word), None))?;
     }
     Ok(index)
 }
 
-fn parse_line(line: &str, line_number: usize) -> Result<(&str, u64, u64), DictError> {
+/// Encode a number in the format described in the module documentation.
+///
+/// This is the inverse of [decode_number](fn.decode_number.html).
+///
+/// # Example
+///
+/// ```
+/// use dict::indexing::encode_number;
+///
+/// assert_eq!(encode_number(226751), "3W/");
+/// ```
+pub fn encode_number(mut number: u64) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(BASE64_DIGITS[(number % 64) as usize]);
+        number /= 64;
+        if number == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().map(|d| *d as char).collect()
+}
+
+fn parse_line(line: &str, line_number: usize)
+        -> Result<(&str, u64, u64, Option<&str>), DictError> {
     let mut split = line.split('\t');
     let word = split.next().ok_or(MissingColumnInIndex(line_number))?;
 
@@ -80,22 +150,164 @@ fn parse_line(line: &str, line_number: usize) -> Result<(&str, u64, u64), DictEr
     let length = split.next().ok_or(MissingColumnInIndex(line_number))?;
     let length = decode_number(length)?;
 
-    Ok((word, start_offset, length))
+    // optional fourth column: original headword
+    let original = split.next().filter(|original| !original.is_empty() && *original != word);
+
+    Ok((word, start_offset, length, original))
+}
+
+/// A single parsed line of an index file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexEntry {
+    /// headword as given in the first column
+    pub headword: String,
+    /// position of the definition and the original headword, if given
+    pub location: Location,
+    /// number of the line, starting at 1
+    pub line_number: usize,
+    /// the line as read from the index, without the line break
+    pub line: String,
+}
+
+/// Iterator over the lines of an index file.
+///
+/// Each line is parsed on its own, so an invalid line does not stop the iteration. Errors of
+/// single lines are reported as `DictError::InvalidIndexLine`, containing the line number (starting
+/// at 1), the raw line and the error found in the line. The latter is the same as from
+/// [parse_index](fn.parse_index.html), e.g. `MissingColumnInIndex` with a line number starting at
+/// 0. I/O errors are returned unchanged; the iteration should be stopped after them.
+pub struct IndexEntries<B: BufRead> {
+    reader: B,
+    line_number: usize,
+    buffer: Vec<u8>,
+}
+
+impl<B: BufRead> IndexEntries<B> {
+    /// Iterate over the entries of the index read from `reader`.
+    pub fn new(reader: B) -> IndexEntries<B> {
+        IndexEntries { reader, line_number: 0, buffer: Vec::new() }
+    }
+}
+
+impl<B: BufRead> Iterator for IndexEntries<B> {
+    type Item = Result<IndexEntry, DictError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffer.clear();
+        match self.reader.read_until(b'\n', &mut self.buffer) {
+            Ok(0) => return None,
+            Ok(_) => (),
+            Err(e) => return Some(Err(e.into())),
+        }
+        self.line_number += 1;
+        let mut bytes = &self.buffer[..];
+        if let Some(stripped) = bytes.strip_suffix(b"\n") {
+            bytes = stripped.strip_suffix(b"\r").unwrap_or(stripped);
+        }
+
+        let line_number = self.line_number;
+        let line = match String::from_utf8(bytes.to_vec()) {
+            Ok(line) => line,
+            Err(e) => return Some(Err(InvalidIndexLine(line_number,
+                    String::from_utf8_lossy(bytes).into_owned(), Box::new(e.into())))),
+        };
+        let parsed = parse_line(&line, line_number - 1).map(|(word, offset, length, original)| {
+            let original = original.map(String::from);
+            (word.to_string(), Location { offset, length, original })
+        });
+        Some(match parsed {
+            Ok((headword, location)) => Ok(IndexEntry { headword, location, line_number, line }),
+            Err(e) => Err(InvalidIndexLine(line_number, line, Box::new(e))),
+        })
+    }
 }
 
 /// Parse the index for a dictionary from a given BufRead compatible object.
+///
+/// The first invalid line aborts the parsing, e.g. with `DictError::MissingColumnInIndex`. To get
+/// the line number and text of invalid lines, use [IndexEntries](struct.IndexEntries.html).
 pub fn parse_index<B: BufRead>(br: B) -> Result<Index, DictError> {
-    let mut index = HashMap::new();
+    let mut index = Index::new();
 
     for (line_number, line) in br.lines().enumerate() {
         let line = line?;
-        let (word, start_offset, length) = parse_line(&line, line_number)?;
-        index.entry(word.to_string()).or_insert((start_offset, length));
+        let (word, offset, length, original) = parse_line(&line, line_number)?;
+        let original = original.map(String::from);
+        index.entry(word.to_string()).or_default().push(Location { offset, length, original });
     }
 
     Ok(index)
 }
 
+/// Parse the index for a dictionary, skipping invalid lines.
+///
+/// The errors of all skipped lines are returned as warnings, next to the index built from the
+/// valid lines. I/O errors still abort the parsing.
+pub fn parse_index_lenient<B: BufRead>(br: B) -> Result<(Index, Vec<DictError>), DictError> {
+    let mut index = Index::new();
+    let mut warnings = Vec::new();
+
+    for entry in IndexEntries::new(br) {
+        match entry {
+            Ok(entry) => index.entry(entry.headword).or_default().push(entry.location),
+            Err(e @ InvalidIndexLine(..)) => warnings.push(e),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok((index, warnings))
+}
+
+/// Normalise all headwords of an index.
+///
+/// Each headword is replaced by the result of `normalize`. The spelling from the index is kept in
+/// the `original` field of its locations, if it differs from the new key. For locations which
+/// already carry an original spelling, this one is normalised instead, so an index can be
+/// normalised again with different rules.
+///
+/// Locations of headwords which become equal after normalisation are merged and ordered by their
+/// offset, since the order of the index file is not known any more. Locations of a single
+/// headword keep their order.
+pub fn normalize_index<F: Fn(&str) -> String>(index: &mut Index, normalize: F) {
+    let mut moved = Vec::new();
+    let keys: Vec<String> = index.keys().cloned().collect();
+    for key in keys {
+        let unchanged = index[&key].iter().all(|l| match l.original {
+            Some(ref original) => normalize(original) == key,
+            None => true,
+        }) && normalize(&key) == key;
+        if unchanged {
+            continue;
+        }
+        for mut location in index.remove(&key).unwrap_or_default() {
+            let source = location.original.take().unwrap_or_else(|| key.clone());
+            let normalized = normalize(&source);
+            if normalized != source {
+                location.original = Some(source);
+            }
+            moved.push((normalized, key.clone(), location));
+        }
+    }
+
+    // only locations from different keys are reordered, homographs of one key keep their order
+    let mut first_source: HashMap<String, String> = HashMap::new();
+    let mut merged = HashSet::new();
+    for (key, source, location) in moved {
+        let locations = index.entry(key.clone()).or_default();
+        if !locations.is_empty() && first_source.get(&key) != Some(&source) {
+            merged.insert(key.clone());
+        }
+        locations.push(location);
+        first_source.entry(key).or_insert(source);
+    }
+    for key in merged {
+        if let Some(locations) = index.get_mut(&key) {
+            locations.sort_by_key(|l| l.offset);
+        }
+    }
+}
+
 /// Parse the index for a dictionary from a given path.
 pub fn parse_index_from_file<P: AsRef<Path>>(path: P) -> Result<Index, DictError> {
     let file = File::open(path)?;
@@ -103,3 +315,72 @@ pub fn parse_index_from_file<P: AsRef<Path>>(path: P) -> Result<Index, DictError
     parse_index(file)
 }
 
+/// Parse the index for a dictionary from a given path, skipping invalid lines.
+///
+/// See [parse_index_lenient](fn.parse_index_lenient.html).
+pub fn parse_index_from_file_lenient<P: AsRef<Path>>(path: P)
+        -> Result<(Index, Vec<DictError>), DictError> {
+    let file = File::open(path)?;
+    parse_index_lenient(BufReader::new(&file))
+}
+
+/// List all locations of an index with their (normalised) headword.
+pub(crate) fn entries(index: &Index) -> Vec<(&str, &Location)> {
+    index.iter()
+        .flat_map(|(key, locations)| locations.iter().map(move |l| (key.as_str(), l)))
+        .collect()
+}
+
+/// Serialise a list of locations in the little-endian binary format used by the index caches.
+pub(crate) fn write_locations<W: Write>(writer: &mut W, locations: &[Location])
+        -> Result<(), DictError> {
+    writer.write_u32::<LittleEndian>(locations.len() as u32)?;
+    for location in locations {
+        writer.write_u64::<LittleEndian>(location.offset)?;
+        writer.write_u64::<LittleEndian>(location.length)?;
+        match location.original {
+            Some(ref original) => write_string(writer, original)?,
+            None => writer.write_u32::<LittleEndian>(u32::MAX)?,
+        }
+    }
+    Ok(())
+}
+
+/// Deserialise a list of locations written by `write_locations`.
+pub(crate) fn read_locations<R: Read>(reader: &mut R) -> Result<Vec<Location>, DictError> {
+    let count = reader.read_u32::<LittleEndian>()?;
+    let mut locations = Vec::with_capacity(count.min(1024) as usize);
+    for _ in 0..count {
+        let offset = reader.read_u64::<LittleEndian>()?;
+        let length = reader.read_u64::<LittleEndian>()?;
+        let original = match reader.read_u32::<LittleEndian>()? {
+            u32::MAX => None,
+            len => Some(read_string_of_length(reader, len)?),
+        };
+        locations.push(Location { offset, length, original });
+    }
+    Ok(locations)
+}
+
+/// Serialise a string, prefixed by its length.
+pub(crate) fn write_string<W: Write>(writer: &mut W, string: &str) -> Result<(), DictError> {
+    writer.write_u32::<LittleEndian>(string.len() as u32)?;
+    writer.write_all(string.as_bytes())?;
+    Ok(())
+}
+
+/// Deserialise a string written by `write_string`.
+pub(crate) fn read_string<R: Read>(reader: &mut R) -> Result<String, DictError> {
+    let length = reader.read_u32::<LittleEndian>()?;
+    read_string_of_length(reader, length)
+}
+
+fn read_string_of_length<R: Read>(reader: &mut R, length: u32) -> Result<String, DictError> {
+    let mut bytes = Vec::new();
+    reader.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != length as usize {
+        return Err(DictError::IoError(::std::io::Error::new(
+                ::std::io::ErrorKind::UnexpectedEof, "string is truncated")));
+    }
+    Ok(String::from_utf8(bytes)?)
+}
@@ -0,0 +1,178 @@
+//! Export dictionaries to JSON and JSON Lines and import them again.
+//!
+//! A dictionary is dumped as a sequence of [records](enum.Record.html): the metadata from the
+//! `00-database-*` entries comes first, followed by one record per definition. Headwords sharing
+//! a definition are put into the same record, e.g.:
+//!
+//! ```text
+//! {"type":"metadata","short_name":"Latin - German","info":null,"url":null,"utf8":true,...}
+//! {"type":"entry","headword":"mater","alternates":["matris"],"definition":"mater\n Mutter\n",
+//!  "offset":0,"length":14}
+//! ```
+//!
+//! In JSON Lines, each record is written on its own line; in JSON, the records form an array.
+//! The span of each definition in the dict file is exported for reference. On import, the
+//! definitions are written anew with a
+//! [DictionaryBuilder](../builder/struct.DictionaryBuilder.html), so the spans are ignored. This
+//! module is only available with the `serde` feature.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::builder::DictionaryBuilder;
+use crate::errors::DictError;
+use crate::metadata::DatabaseInfo;
+use crate::Dictionary;
+
+/// A record of a JSON (Lines) dump.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Record {
+    /// the metadata of the database, written first
+    Metadata(DatabaseInfo),
+    /// a definition with its headwords
+    Entry(EntryRecord),
+}
+
+/// A definition with its headwords and its span in the dict file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryRecord {
+    /// the headword the definition starts with, or else the first one
+    pub headword: String,
+    /// other headwords with the same definition
+    #[serde(default)]
+    pub alternates: Vec<String>,
+    /// the definition
+    pub definition: String,
+    /// offset of the definition in the dict file
+    #[serde(default)]
+    pub offset: u64,
+    /// length of the definition in the dict file, in bytes
+    #[serde(default)]
+    pub length: u64,
+}
+
+/// Get the records of a dictionary: its metadata and all entries in the order of the dict file.
+///
+/// Meta entries are only exported as part of the metadata.
+pub fn records(dictionary: &mut Dictionary) -> Result<Vec<Record>, DictError> {
+    let mut records = vec![Record::Metadata(dictionary.info()?)];
+    let mut entries: Vec<(Vec<String>, String, (u64, u64))> = Vec::new();
+    let mut iter = dictionary.entries();
+    while let Some(entry) = iter.next() {
+        let (headword, definition) = entry?;
+        let span = iter.last_span().unwrap_or_default();
+        match entries.last_mut() {
+            Some((headwords, _, last)) if *last == span => headwords.push(headword),
+            _ => entries.push((vec![headword], definition, span)),
+        }
+    }
+    records.extend(entries.into_iter().map(|(mut headwords, definition, (offset, length))| {
+        let headword = headwords.remove(crate::main_headword(&headwords, &definition));
+        Record::Entry(EntryRecord { headword, alternates: headwords, definition, offset, length })
+    }));
+    Ok(records)
+}
+
+/// Write a dictionary as JSON Lines.
+pub fn write_jsonl<W: Write>(dictionary: &mut Dictionary, mut writer: W)
+        -> Result<(), DictError> {
+    for record in records(dictionary)? {
+        serde_json::to_writer(&mut writer, &record).map_err(json_error)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Write a dictionary as a JSON array of records.
+pub fn write_json<W: Write>(dictionary: &mut Dictionary, writer: W) -> Result<(), DictError> {
+    serde_json::to_writer(writer, &records(dictionary)?).map_err(json_error)
+}
+
+/// Export a dictionary to a file, as JSON Lines if its name ends on `.jsonl`, else as JSON.
+pub fn export_file<P: AsRef<Path>>(dictionary: &mut Dictionary, path: P)
+        -> Result<(), DictError> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path)?);
+    if is_jsonl(path) {
+        write_jsonl(dictionary, &mut writer)?;
+    } else {
+        write_json(dictionary, &mut writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read records from JSON Lines; empty lines are skipped.
+pub fn read_jsonl<B: BufRead>(reader: B) -> Result<Vec<Record>, DictError> {
+    let mut records = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        records.push(serde_json::from_str(&line).map_err(|e| DictError::InvalidFileFormat(
+                format!("line {}: {}", number + 1, e), None))?);
+    }
+    Ok(records)
+}
+
+/// Read records from a JSON array.
+pub fn read_json<R: Read>(reader: R) -> Result<Vec<Record>, DictError> {
+    serde_json::from_reader(reader).map_err(json_error)
+}
+
+/// Create a builder with the given records.
+///
+/// The metadata record sets the metadata of the builder; if there are several, the last one wins.
+pub fn to_builder(records: &[Record]) -> Result<DictionaryBuilder, DictError> {
+    let mut builder = DictionaryBuilder::new();
+    for record in records {
+        match record {
+            Record::Metadata(info) => builder.set_info(info.clone()),
+            Record::Entry(entry) => {
+                let mut headwords = vec![entry.headword.as_str()];
+                headwords.extend(entry.alternates.iter().map(|a| a.as_str()));
+                builder.add_entry(&headwords, &entry.definition)?;
+            },
+        }
+    }
+    Ok(builder)
+}
+
+/// Import a JSON or JSON Lines file, chosen by the extension `.jsonl`, into a dict file and an
+/// index.
+///
+/// The dict file is compressed if its name ends on `.dz`.
+pub fn convert_file<P: AsRef<Path>>(json_path: P, dict_path: P, index_path: P)
+        -> Result<(), DictError> {
+    to_builder(&read_file(json_path.as_ref())?)?.write_files(dict_path, index_path)
+}
+
+/// Load a JSON or JSON Lines file, chosen by the extension `.jsonl`, as an in-memory dictionary.
+pub fn load_file<P: AsRef<Path>>(json_path: P) -> Result<Dictionary, DictError> {
+    to_builder(&read_file(json_path.as_ref())?)?.build()
+}
+
+fn read_file(path: &Path) -> Result<Vec<Record>, DictError> {
+    let reader = BufReader::new(File::open(path)?);
+    if is_jsonl(path) {
+        read_jsonl(reader)
+    } else {
+        read_json(reader)
+    }
+}
+
+fn is_jsonl(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "jsonl")
+}
+
+fn json_error(error: serde_json::Error) -> DictError {
+    if error.is_io() {
+        DictError::IoError(error.into())
+    } else {
+        DictError::InvalidFileFormat(error.to_string(), None)
+    }
+}
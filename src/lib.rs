@@ -8,24 +8,52 @@
 //! The usage is straight forward:
 //!
 //! ```rust,no_run
-//! fn main() {
-//!     let index_file = "/usr/share/dictd/freedict-lat-deu.index";
-//!     let dict_file = "/usr/share/dictd/freedict-lat-deu.dict.dz";
-//!     let mut latdeu = dict::load_dictionary_from_file(dict_file, index_file).unwrap();
-//!     // hey: rust!
-//!     println!("{}", latdeu.lookup("ferrugo").unwrap());
-//! }
+//! let index_file = "/usr/share/dictd/freedict-lat-deu.index";
+//! let dict_file = "/usr/share/dictd/freedict-lat-deu.dict.dz";
+//! let mut latdeu = dict::load_dictionary_from_file(dict_file, index_file).unwrap();
+//! // hey: rust!
+//! println!("{}", latdeu.lookup("ferrugo").unwrap());
 //! ```
 
+pub mod builder;
+pub mod cache;
+pub mod collation;
+pub mod collection;
+pub mod config;
 pub mod dictreader;
+pub mod dictwriter;
+pub mod dsl;
 pub mod errors;
+#[cfg(feature = "fst")]
+pub mod fstindex;
+pub mod fulltext;
 pub mod indexing;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod matching;
+pub mod metadata;
+pub mod normalizer;
+pub mod scan;
+pub mod stardict;
+pub mod strategy;
+#[cfg(feature = "tei")]
+pub mod tei;
+pub mod validation;
 
+use self::cache::CacheLocation;
+use self::collation::Collation;
 use self::dictreader::DictReader;
+use self::fulltext::{DefinitionIndex, SearchHit};
 use self::indexing::Index;
+use self::matching::{HeadwordIndex, MatchOptions};
+use self::metadata::DatabaseInfo;
+use self::normalizer::Normalizer;
+use self::strategy::{MatchStrategy, StrategyRegistry};
 
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::HashSet;
 use std::path::Path;
-use std::collections::HashMap;
 
 /// A dictionary wrapper.
 ///
@@ -36,7 +64,18 @@ use std::collections::HashMap;
 /// For an example, please see the [crate documentation](index.html).
 pub struct Dictionary {
     dict_reader: Box<dyn DictReader>,
-    word_index: HashMap<String, (u64, u64)>
+    word_index: WordIndex,
+    /// Unicode normalisation of headwords and queries
+    normalizer: Normalizer,
+    /// normalisation rules for headwords and queries
+    collation: Collation,
+    /// auxiliary index for match strategies, built on first use
+    headword_index: OnceCell<HeadwordIndex>,
+    strategies: StrategyRegistry,
+    /// inverted index for full-text searches, built on first use
+    definition_index: Option<DefinitionIndex>,
+    /// metadata given by other means than the meta entries, e.g. from a StarDict `.ifo` file
+    database_info: Option<DatabaseInfo>,
 }
 
 impl Dictionary {
@@ -44,25 +83,382 @@ impl Dictionary {
     ///
     /// Words are looked up in the index and then retrieved from the dict file. If no word was
     /// found, `DictError::WordNotFound` is returned. Other errors all result from the parsing of
-    /// the underlying files.
+    /// the underlying files. If the headword has several definitions, only the first one from the
+    /// index is returned; see [lookup_all](#method.lookup_all).
     pub fn lookup(&mut self, word: &str) -> Result<String, errors::DictError> {
-        let &(start, length) = self.word_index.get(&word.to_lowercase()).ok_or_else(||
-                errors::DictError::WordNotFound(word.into()))?;
-        self.dict_reader.fetch_definition(start, length)
+        let location = self.locations(word)?[0].clone();
+        self.dict_reader.fetch_definition(location.offset, location.length)
+    }
+
+    /// Look up all definitions of a word.
+    ///
+    /// Homographs, e.g. Latin "a" as a preposition and as an interjection, may have several
+    /// entries in the index. This function returns the definitions of all of them, in the order
+    /// of the index. Headwords which only became equal through normalisation, e.g. "Rosa" and
+    /// "rosa", are an exception: the order between their lines is not known any more, so their
+    /// definitions are ordered by their position in the dict file. Errors are the same as for
+    /// [lookup](#method.lookup).
+    pub fn lookup_all(&mut self, word: &str) -> Result<Vec<String>, errors::DictError> {
+        let locations = self.locations(word)?.to_vec();
+        locations.iter()
+            .map(|l| self.dict_reader.fetch_definition(l.offset, l.length))
+            .collect()
+    }
+
+    /// Look up a word and return it together with its headword.
+    ///
+    /// The headword is given in its original spelling, e.g. "Rosa" from the fourth column of an
+    /// index written by `dictfmt --index-keep-orig`, or the key from the index otherwise.
+    /// Apart from that, this behaves like [lookup](#method.lookup).
+    pub fn lookup_with_headword(&mut self, word: &str)
+            -> Result<(String, String), errors::DictError> {
+        Ok(self.lookup_all_with_headwords(word)?.swap_remove(0))
+    }
+
+    /// Look up all definitions of a word together with their headwords.
+    ///
+    /// Homographs may differ in their original spelling, so each definition comes with its own
+    /// headword. See [lookup_with_headword](#method.lookup_with_headword).
+    pub fn lookup_all_with_headwords(&mut self, word: &str)
+            -> Result<Vec<(String, String)>, errors::DictError> {
+        let key = self.normalize(word);
+        let locations = self.locations(word)?.to_vec();
+        locations.into_iter()
+            .map(|l| {
+                let definition = self.dict_reader.fetch_definition(l.offset, l.length)?;
+                Ok((l.original.unwrap_or_else(|| key.clone()), definition))
+            })
+            .collect()
+    }
+
+    /// Look up all definitions of a word, with headword, database and span of each.
+    ///
+    /// Unlike the other lookup methods, the results can be serialised with the `serde` feature,
+    /// e.g. to send them to another process.
+    pub fn lookup_results(&mut self, word: &str)
+            -> Result<Vec<LookupResult>, errors::DictError> {
+        let locations = self.locations(word)?.to_vec();
+        let database = match self.short_name() {
+            Ok(name) => name,
+            Err(errors::DictError::WordNotFound(_)) => String::new(),
+            Err(e) => return Err(e),
+        };
+        let key = self.normalize(word);
+        locations.into_iter()
+            .map(|l| Ok(LookupResult {
+                definition: self.dict_reader.fetch_definition(l.offset, l.length)?,
+                headword: l.original.unwrap_or_else(|| key.clone()),
+                database: database.clone(),
+                offset: l.offset,
+                length: l.length,
+            }))
+            .collect()
+    }
+
+    fn locations(&self, word: &str) -> Result<Cow<'_, [indexing::Location]>, errors::DictError> {
+        self.word_index.get(&self.normalize(word))
+            .filter(|locations| !locations.is_empty())
+            .ok_or_else(|| errors::DictError::WordNotFound(word.into()))
     }
 
     /// Check whether a word is contained in the index
     pub fn contains(&self, word: &str) -> bool {
-        self.word_index.get(&word.to_lowercase()).is_some()
+        self.word_index.contains_key(&self.normalize(word))
     }
 
     /// Case-sensitive member check.
     ///
-    /// This will check whether the given word is contained in the index, without checking whether
-    /// it's lower case or not. This can help to avoid an additional allocation, if the caller can
-    /// be sure that the string is already lower case.
+    /// This will check whether the given word is contained in the index, without normalising it
+    /// first. This can help to avoid an additional allocation, if the caller can be sure that the
+    /// string is already normalised, see [normalize](#method.normalize).
     pub fn contains_unchecked(&self, word: &str) -> bool {
-        self.word_index.get(word).is_some()
+        self.word_index.contains_key(word)
+    }
+
+    /// Find all headwords matching a regular expression.
+    ///
+    /// This corresponds to the `re` strategy of dictd, with the syntax of the regex crate, see
+    /// [compile_regex](matching/fn.compile_regex.html). The expression is not anchored, so `"tio$"`
+    /// finds all headwords ending on "tio". Headwords are returned in sorted order; the search is
+    /// bounded by the given [MatchOptions](matching/struct.MatchOptions.html).
+    pub fn match_regex(&self, pattern: &str, options: &MatchOptions)
+            -> Result<Vec<String>, errors::DictError> {
+        self.find_with(&strategy::RegularExpression, pattern, options)
+    }
+
+    /// Find all headwords matching a glob-style pattern.
+    ///
+    /// See [compile_glob](matching/fn.compile_glob.html) for the supported syntax. Headwords are
+    /// returned in sorted order; the search is bounded by the given
+    /// [MatchOptions](matching/struct.MatchOptions.html).
+    pub fn match_glob(&self, pattern: &str, options: &MatchOptions)
+            -> Result<Vec<String>, errors::DictError> {
+        self.find_with(&strategy::Glob, pattern, options)
+    }
+
+    /// Find all headwords ending on the given suffix.
+    ///
+    /// This corresponds to the `suffix` strategy of dictd. Headwords are returned in sorted
+    /// order; the number of results is bounded by the given
+    /// [MatchOptions](matching/struct.MatchOptions.html).
+    pub fn match_suffix(&self, suffix: &str, options: &MatchOptions)
+            -> Result<Vec<String>, errors::DictError> {
+        self.find_with(&strategy::Suffix, suffix, options)
+    }
+
+    /// Find all headwords containing the given string.
+    ///
+    /// This corresponds to the `substring` strategy of dictd. Headwords are returned in sorted
+    /// order; the search is bounded by the given
+    /// [MatchOptions](matching/struct.MatchOptions.html).
+    pub fn match_substring(&self, substring: &str, options: &MatchOptions)
+            -> Result<Vec<String>, errors::DictError> {
+        self.find_with(&strategy::Substring, substring, options)
+    }
+
+    /// Find all headwords matching `query` with the named strategy.
+    ///
+    /// The strategy is looked up in the [registry](#method.strategies) of this dictionary; if it
+    /// is unknown, `DictError::UnknownStrategy` is returned.
+    pub fn match_words(&self, strategy: &str, query: &str, options: &MatchOptions)
+            -> Result<Vec<String>, errors::DictError> {
+        let strategy = self.strategies.get(strategy).ok_or_else(||
+                errors::DictError::UnknownStrategy(strategy.into()))?;
+        self.find_with(strategy, query, options)
+    }
+
+    fn find_with(&self, strategy: &dyn MatchStrategy, query: &str, options: &MatchOptions)
+            -> Result<Vec<String>, errors::DictError> {
+        let query = if strategy.takes_pattern() {
+            Cow::Borrowed(query)
+        } else {
+            Cow::Owned(self.normalize(query))
+        };
+        // a compact index is searched directly by the strategies which support it
+        #[cfg(feature = "fst")]
+        let compact = match self.word_index {
+            WordIndex::Fst(ref index) => strategy.find_compact(&query, index, options),
+            _ => None,
+        };
+        #[cfg(not(feature = "fst"))]
+        let compact: Option<Result<Vec<String>, errors::DictError>> = None;
+        let keys = match compact {
+            Some(keys) => keys?,
+            None => strategy.find(&query, self.headword_index(), options)?,
+        };
+        // report the headwords as given in the index, not the normalised keys; keys which are not
+        // in the index, e.g. from a faulty custom strategy, are skipped
+        let mut headwords = Vec::with_capacity(keys.len());
+        let mut seen = HashSet::with_capacity(keys.len());
+        for key in keys {
+            let locations = match self.word_index.get(&key) {
+                Some(locations) => locations,
+                None => continue,
+            };
+            for location in locations.iter() {
+                let headword = location.original.as_ref().unwrap_or(&key);
+                if seen.insert(headword.clone()) {
+                    headwords.push(headword.clone());
+                }
+            }
+        }
+        headwords.truncate(options.limit);
+        Ok(headwords)
+    }
+
+    /// Normalise a word the way headwords of this dictionary are normalised.
+    ///
+    /// The word is first normalised with the [Normalizer](normalizer/struct.Normalizer.html) and
+    /// then according to the [collation](#method.collation) of the dictionary.
+    pub fn normalize(&self, word: &str) -> String {
+        self.collation.normalize(&self.normalizer.normalize(word))
+    }
+
+    /// Get the Unicode normalisation settings of this dictionary.
+    pub fn normalizer(&self) -> Normalizer {
+        self.normalizer
+    }
+
+    /// Change the Unicode normalisation settings.
+    ///
+    /// All headwords of the index are normalised again, so that subsequent lookups and matches
+    /// use the new settings consistently. Matches still return the headwords as given in the
+    /// index.
+    pub fn set_normalizer(&mut self, normalizer: Normalizer) {
+        self.normalizer = normalizer;
+        let (normalizer, collation) = (self.normalizer, self.collation);
+        self.word_index.normalize(|w| collation.normalize(&normalizer.normalize(w)));
+        self.headword_index = OnceCell::new();
+    }
+
+    /// Keep the index in a finite-state transducer instead of a hash map.
+    ///
+    /// This takes considerably less memory, which matters when many dictionaries are loaded at
+    /// once, at the price of slightly slower lookups. See the [fstindex](fstindex/index.html)
+    /// module. The `exact`, `prefix` and `lev` strategies search the transducer directly, other
+    /// match strategies build their auxiliary index of all headwords on first use.
+    /// This is only available with the `fst` feature.
+    #[cfg(feature = "fst")]
+    pub fn compact_index(&mut self) -> Result<(), errors::DictError> {
+        if let WordIndex::Map(ref index) = self.word_index {
+            self.word_index = WordIndex::Fst(fstindex::FstIndex::from_index(index)?);
+        }
+        Ok(())
+    }
+
+    /// Get the normalisation rules of this dictionary.
+    ///
+    /// The rules are selected from the `00-database-allchars` and `00-database-case-sensitive`
+    /// entries of the index, just like dictd does. Headwords and queries are normalised with these
+    /// rules.
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+
+    /// Get the match strategies available for this dictionary.
+    ///
+    /// By default, these are the strategies from the [strategy](strategy/index.html) module.
+    pub fn strategies(&self) -> &StrategyRegistry {
+        &self.strategies
+    }
+
+    /// Make a custom match strategy available, replacing any strategy with the same name.
+    pub fn register_strategy(&mut self, strategy: Box<dyn MatchStrategy>) {
+        self.strategies.register(strategy);
+    }
+
+    fn headword_index(&self) -> &HeadwordIndex {
+        self.headword_index.get_or_init(|| match self.word_index {
+            WordIndex::Map(ref index) => HeadwordIndex::new(index.keys().map(|w| w.as_str())),
+            #[cfg(feature = "fst")]
+            WordIndex::Fst(ref index) =>
+                HeadwordIndex::new(index.keys().iter().map(|w| w.as_str())),
+        })
+    }
+
+    /// All keys of the index in sorted order; a transducer is listed directly.
+    fn sorted_keys(&self) -> Cow<'_, [String]> {
+        #[cfg(feature = "fst")]
+        if let WordIndex::Fst(ref index) = self.word_index {
+            return Cow::Owned(index.keys());
+        }
+        Cow::Borrowed(self.headword_index().headwords())
+    }
+
+    /// Get all headwords in collation order.
+    ///
+    /// Headwords are given in their original spelling, each one only once. Meta entries like
+    /// `00-database-short` are skipped, see [all_headwords](#method.all_headwords).
+    pub fn headwords(&self) -> Vec<String> {
+        self.collect_headwords(false)
+    }
+
+    /// Get all headwords in collation order, including meta entries.
+    pub fn all_headwords(&self) -> Vec<String> {
+        self.collect_headwords(true)
+    }
+
+    fn collect_headwords(&self, include_meta: bool) -> Vec<String> {
+        let mut headwords = Vec::with_capacity(self.word_index.len());
+        for key in self.sorted_keys().iter() {
+            if !include_meta && validation::is_meta_headword(key) {
+                continue;
+            }
+            let start = headwords.len();
+            for location in self.word_index.get(key).iter().flat_map(|l| l.iter()) {
+                let headword = location.original.as_ref().unwrap_or(key);
+                if !headwords[start..].contains(headword) {
+                    headwords.push(headword.clone());
+                }
+            }
+            headwords[start..].sort();
+        }
+        headwords
+    }
+
+    /// Iterate over all entries as (headword, definition) pairs.
+    ///
+    /// Entries are read in the order of the dict file, so that each chunk of a compressed file is
+    /// inflated only once. A headword with several definitions occurs once for each of them.
+    /// Meta entries are skipped, see [all_entries](#method.all_entries).
+    pub fn entries(&mut self) -> Entries<'_> {
+        self.collect_entries(false)
+    }
+
+    /// Iterate over all entries, including meta entries.
+    pub fn all_entries(&mut self) -> Entries<'_> {
+        self.collect_entries(true)
+    }
+
+    fn collect_entries(&mut self, include_meta: bool) -> Entries<'_> {
+        let mut entries: Vec<(String, u64, u64)> = self.word_index.with_entries(|entries|
+                entries.iter()
+                    .filter(|(key, _)| include_meta || !validation::is_meta_headword(key))
+                    .map(|(key, l)|
+                        (l.original.as_deref().unwrap_or(key).to_string(), l.offset, l.length))
+                    .collect());
+        entries.sort_unstable_by(|a, b| (a.1, a.2, &a.0).cmp(&(b.1, b.2, &b.0)));
+        Entries { reader: self.dict_reader.as_mut(), entries: entries.into_iter(),
+                previous: None }
+    }
+
+    /// Search the definitions for the terms of `query`.
+    ///
+    /// This finds headwords by their translation, e.g. "ferrugo" for "Rost". Hits are ranked by
+    /// relevance, see the [fulltext](fulltext/index.html) module. On first use, all definitions
+    /// are read to build the inverted index; use
+    /// [set_definition_index](#method.set_definition_index) to supply a previously saved one.
+    pub fn search_definitions(&mut self, query: &str)
+            -> Result<Vec<SearchHit>, errors::DictError> {
+        Ok(self.definition_index()?.search(query))
+    }
+
+    /// Get the inverted index over all definitions, building it if required.
+    ///
+    /// The index can be saved and loaded again, see
+    /// [DefinitionIndex](fulltext/struct.DefinitionIndex.html).
+    pub fn definition_index(&mut self) -> Result<&DefinitionIndex, errors::DictError> {
+        if self.definition_index.is_none() {
+            let reader = self.dict_reader.as_mut();
+            let index = self.word_index.with_entries(|entries|
+                    DefinitionIndex::build_from_entries(reader, entries))?;
+            self.definition_index = Some(index);
+        }
+        Ok(self.definition_index.as_ref().unwrap())
+    }
+
+    /// Use a previously built inverted index for full-text searches.
+    ///
+    /// An index built for other dictionary data, e.g. before the dictionary was updated, is
+    /// refused with `DictError::InvalidFileFormat`; it is then built anew on the next search.
+    pub fn set_definition_index(&mut self, index: DefinitionIndex)
+            -> Result<(), errors::DictError> {
+        let reader = self.dict_reader.as_ref();
+        if !self.word_index.with_entries(|entries| index.matches_entries(reader, entries)) {
+            return Err(errors::DictError::InvalidFileFormat("the definition index was built \
+                    for a different version of the dictionary".into(), None));
+        }
+        self.definition_index = Some(index);
+        Ok(())
+    }
+
+    /// Check the index against the dict data.
+    ///
+    /// All definitions are read once, in the order of the dict file. The returned list contains
+    /// entries beyond the end of the data, overlapping or shared definitions, unreferenced bytes,
+    /// definitions not starting with their headword, invalid UTF-8 and missing meta entries. See
+    /// the [validation](validation/index.html) module for details.
+    ///
+    /// Missing meta entries are not reported if the metadata was set with
+    /// [set_info](#method.set_info).
+    pub fn validate(&mut self) -> Result<Vec<validation::Issue>, errors::DictError> {
+        let (reader, collation) = (self.dict_reader.as_mut(), &self.collation);
+        let mut issues = self.word_index.with_entries(|entries|
+                validation::validate_entries(reader, entries, collation))?;
+        if self.database_info.is_some() {
+            issues.retain(|i| !matches!(i, validation::Issue::MissingMetaEntry(_)));
+        }
+        Ok(issues)
     }
 
     /// Get the short name.
@@ -70,29 +466,259 @@ impl Dictionary {
     /// This returns the short name of a dictionary. This corresponds to the
     /// value passed to the `-s` option of `dictfmt`.
     pub fn short_name(&mut self) -> Result<String, errors::DictError> {
-        self.lookup("00-database-short")
-            .or_else(|_| self.lookup("00databaseshort"))
-            // Some dictionaries contain the headword in their entry, others don't:
-            .map(|def| {
-                let start = if def.starts_with("00-database-short") {
-                    17
-                } else {
-                    0
+        if let Some(ref info) = self.database_info {
+            return info.short_name.clone()
+                .ok_or_else(|| errors::DictError::WordNotFound(metadata::SHORT.into()));
+        }
+        self.meta_entry(metadata::SHORT)?
+            .ok_or_else(|| errors::DictError::WordNotFound(metadata::SHORT.into()))
+    }
+
+    /// Get the metadata of the database from its `00-database-*` entries.
+    ///
+    /// Missing entries are not an error, the corresponding fields are left empty. If the metadata
+    /// was set with [set_info](#method.set_info), it is returned instead.
+    pub fn info(&mut self) -> Result<DatabaseInfo, errors::DictError> {
+        if let Some(ref info) = self.database_info {
+            return Ok(info.clone());
+        }
+        Ok(DatabaseInfo {
+            short_name: self.meta_entry(metadata::SHORT)?,
+            info: self.meta_entry(metadata::INFO)?,
+            url: self.meta_entry(metadata::URL)?,
+            utf8: self.contains(metadata::UTF8),
+            eight_bit: self.contains(metadata::EIGHT_BIT),
+            alphabet: self.meta_entry(metadata::ALPHABET)?,
+            allchars: self.contains(metadata::ALLCHARS),
+            case_sensitive: self.contains(metadata::CASE_SENSITIVE),
+            dictfmt_version: self.dictfmt_version()?,
+        })
+    }
+
+    /// Set the metadata of the database.
+    ///
+    /// This is meant for formats which don't store metadata in `00-database-*` entries. The
+    /// metadata is returned by [info](#method.info) and [short_name](#method.short_name)
+    /// afterwards; it doesn't change the index or the collation.
+    pub fn set_info(&mut self, info: DatabaseInfo) {
+        self.database_info = Some(info);
+    }
+
+    /// Get the value of a meta entry, given with dashes, or `None` if it doesn't exist.
+    fn meta_entry(&mut self, name: &str) -> Result<Option<String>, errors::DictError> {
+        match self.lookup(name) {
+            Ok(definition) => Ok(Some(metadata::meta_value(&definition, name))),
+            Err(errors::DictError::WordNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the version from the `00-database-dictfmt-*` entry.
+    ///
+    /// The dots of the version are usually lost in the normalised headword, so the original
+    /// spelling or the definition is preferred.
+    fn dictfmt_version(&mut self) -> Result<Option<String>, errors::DictError> {
+        let prefix = self.normalize(metadata::DICTFMT_PREFIX);
+        let key = match self.word_index.first_with_prefix(&prefix) {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let location = match self.word_index.get(&key).and_then(|l| l.first().cloned()) {
+            Some(location) => location,
+            None => return Ok(None),
+        };
+        if let Some(version) = location.original.as_ref()
+                .and_then(|o| o.strip_prefix(metadata::DICTFMT_PREFIX)) {
+            return Ok(Some(version.to_string()));
+        }
+        let definition = self.dict_reader.fetch_definition(location.offset, location.length)?;
+        Ok(Some(match definition.trim().strip_prefix(metadata::DICTFMT_PREFIX) {
+            Some(version) => version.to_string(),
+            None => key[prefix.len()..].to_string(),
+        }))
+    }
+}
+
+/// The index of a dictionary, either as a hash map or, with the `fst` feature, as a compact
+/// finite-state transducer.
+enum WordIndex {
+    Map(Index),
+    #[cfg(feature = "fst")]
+    Fst(fstindex::FstIndex),
+}
+
+impl WordIndex {
+    fn get(&self, key: &str) -> Option<Cow<'_, [indexing::Location]>> {
+        match *self {
+            WordIndex::Map(ref index) => index.get(key).map(|l| Cow::Borrowed(l.as_slice())),
+            #[cfg(feature = "fst")]
+            WordIndex::Fst(ref index) => index.get(key).map(Cow::Owned),
+        }
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        match *self {
+            WordIndex::Map(ref index) => index.contains_key(key),
+            #[cfg(feature = "fst")]
+            WordIndex::Fst(ref index) => index.contains(key),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match *self {
+            WordIndex::Map(ref index) => index.len(),
+            #[cfg(feature = "fst")]
+            WordIndex::Fst(ref index) => index.len(),
+        }
+    }
+
+    /// The first key in sorted order starting with `prefix`.
+    fn first_with_prefix(&self, prefix: &str) -> Option<String> {
+        match *self {
+            WordIndex::Map(ref index) => index.keys().filter(|k| k.starts_with(prefix)).min()
+                .cloned(),
+            #[cfg(feature = "fst")]
+            WordIndex::Fst(ref index) => index.prefix(prefix).into_iter().next(),
+        }
+    }
+
+    /// Pass the locations of all keys to `f`; a transducer is listed without building a hash map.
+    fn with_entries<R, F>(&self, f: F) -> R
+            where F: FnOnce(&[(&str, &indexing::Location)]) -> R {
+        match *self {
+            WordIndex::Map(ref index) => f(&indexing::entries(index)),
+            #[cfg(feature = "fst")]
+            WordIndex::Fst(ref index) => {
+                let entries = index.entries();
+                f(&entries.iter().map(|(key, l)| (key.as_str(), l)).collect::<Vec<_>>())
+            },
+        }
+    }
+
+    fn normalize<F: Fn(&str) -> String>(&mut self, normalize: F) {
+        match *self {
+            WordIndex::Map(ref mut index) => indexing::normalize_index(index, normalize),
+            #[cfg(feature = "fst")]
+            WordIndex::Fst(ref index) => {
+                let mut unpacked = index.to_index();
+                indexing::normalize_index(&mut unpacked, normalize);
+                // the keys are sorted and unique, so building the transducer does not fail
+                *self = match fstindex::FstIndex::from_index(&unpacked) {
+                    Ok(index) => WordIndex::Fst(index),
+                    Err(_) => WordIndex::Map(unpacked),
                 };
-                def[start..].trim().to_string()
-            })
+            },
+        }
+    }
+}
+
+/// A definition found for a word, with the database it comes from.
+///
+/// See [Dictionary::lookup_results](struct.Dictionary.html#method.lookup_results).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LookupResult {
+    /// headword in its original spelling, if known, else as normalised in the index
+    pub headword: String,
+    /// short name of the database, empty if the database has none
+    pub database: String,
+    /// the definition
+    pub definition: String,
+    /// offset of the definition in the dict file
+    pub offset: u64,
+    /// length of the definition in bytes
+    pub length: u64,
+}
+
+/// Iterator over the entries of a dictionary.
+///
+/// See [Dictionary::entries](struct.Dictionary.html#method.entries).
+pub struct Entries<'a> {
+    reader: &'a mut dyn DictReader,
+    /// headword, offset and length of the remaining entries
+    entries: std::vec::IntoIter<(String, u64, u64)>,
+    /// span and definition read last, shared by consecutive headwords with the same definition
+    previous: Option<((u64, u64), String)>,
+}
+
+impl Entries<'_> {
+    /// Get the offset and length of the definition returned last.
+    ///
+    /// Headwords sharing a definition are returned one after the other, so they can be grouped by
+    /// their span.
+    pub fn last_span(&self) -> Option<(u64, u64)> {
+        self.previous.as_ref().map(|(span, _)| *span)
+    }
+}
+
+impl Iterator for Entries<'_> {
+    type Item = Result<(String, String), errors::DictError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (headword, offset, length) = self.entries.next()?;
+        if let Some((span, ref definition)) = self.previous {
+            if span == (offset, length) {
+                return Some(Ok((headword, definition.clone())));
+            }
+        }
+        Some(self.reader.fetch_definition(offset, length).map(|definition| {
+            self.previous = Some(((offset, length), definition.clone()));
+            (headword, definition)
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
     }
 }
 
+/// Find the headword a definition starts with, ignoring case; the first one if there is none.
+///
+/// Exporters use it to pick the headword under which a definition shared by several headwords is
+/// stored.
+pub(crate) fn main_headword(headwords: &[String], definition: &str) -> usize {
+    let definition = definition.to_lowercase();
+    headwords.iter().position(|h| definition.starts_with(&h.to_lowercase())).unwrap_or(0)
+}
+
 /// Load dictionary from given paths
 ///
 /// A dictionary is made of an index and a dictionary (data) file, both are opened from the given
 /// input file names. Gzipped files with the suffix `.dz` will be handled automatically.
+///
+/// The index is always parsed; use
+/// [load_dictionary_from_file_cached](fn.load_dictionary_from_file_cached.html) to use an index
+/// cache.
 pub fn load_dictionary_from_file<P: AsRef<Path>>(content_fn: P, index_fn: P) -> Result<Dictionary,
             errors::DictError> {
     let dreader = dictreader::load_dict(content_fn)?;
     let index = indexing::parse_index_from_file(index_fn)?;
-    Ok(Dictionary { dict_reader: dreader, word_index: index })
+    Ok(load_dictionary(dreader, index))
+}
+
+/// Load dictionary from given paths, skipping invalid lines of the index.
+///
+/// This works like [load_dictionary_from_file](fn.load_dictionary_from_file.html), but the index
+/// is parsed with [parse_index_lenient](indexing/fn.parse_index_lenient.html): invalid lines are
+/// returned as warnings next to the dictionary instead of aborting the loading. An index cache is
+/// not used.
+pub fn load_dictionary_from_file_lenient<P: AsRef<Path>>(content_fn: P, index_fn: P)
+        -> Result<(Dictionary, Vec<errors::DictError>), errors::DictError> {
+    let dreader = dictreader::load_dict(content_fn)?;
+    let (index, warnings) = indexing::parse_index_from_file_lenient(index_fn)?;
+    Ok((load_dictionary(dreader, index), warnings))
+}
+
+/// Load dictionary from given paths, using an index cache.
+///
+/// This works like [load_dictionary_from_file](fn.load_dictionary_from_file.html), but the parsed
+/// index is always cached at the given location. See the [cache](cache/index.html) module for
+/// details.
+pub fn load_dictionary_from_file_cached<P: AsRef<Path>>(content_fn: P, index_fn: P,
+            location: &CacheLocation) -> Result<Dictionary, errors::DictError> {
+    let dreader = dictreader::load_dict(content_fn)?;
+    let index = cache::load_index_cached(index_fn, location)?;
+    Ok(load_dictionary(dreader, index))
 }
 
 /// Load dictionary from given [DictReader](dictreader/index.html) and [Index](indexing/type.Index.html).
@@ -101,8 +727,19 @@ pub fn load_dictionary_from_file<P: AsRef<Path>>(content_fn: P, index_fn: P) ->
 /// function allows abstraction from the underlying source by only requiring a
 /// [dictReader](dictreader) as trait object. This way, dictionaries from RAM or similar can be
 /// implemented.
+///
+/// Headwords of the index are normalised with the default
+/// [Normalizer](normalizer/struct.Normalizer.html) and according to the rules selected by its meta
+/// entries, see [collation](collation/index.html).
 pub fn load_dictionary(content: Box<dyn DictReader>, index: Index) -> Dictionary {
-    Dictionary { dict_reader: content, word_index: index }
+    let collation = Collation::from_index(&index);
+    let mut dictionary = Dictionary { dict_reader: content, word_index: WordIndex::Map(index),
+            collation,
+            normalizer: Normalizer::default(), headword_index: OnceCell::new(),
+            strategies: StrategyRegistry::default(), definition_index: None,
+            database_info: None };
+    dictionary.set_normalizer(Normalizer::default());
+    dictionary
 }
 
 #[cfg(test)]
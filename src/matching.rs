@@ -0,0 +1,393 @@
+//! Match headwords against search patterns.
+//!
+//! Apart from looking up a single word, the DICT protocol allows clients to ask for all headwords
+//! matching a query with a given strategy. This module implements the pattern-based strategies
+//! `re` (regular expressions, like dictd's POSIX extended ones but in the syntax of the regex
+//! crate) and glob-style matching (`*`, `?` and `[...]`), as well as the `suffix` and `substring`
+//! strategies.
+//!
+//! Pattern-based strategies have to test every headword. Suffix and substring matches instead use
+//! a [HeadwordIndex](struct.HeadwordIndex.html), which keeps the headwords sorted by their
+//! reversed spelling and maps each trigram (sequence of three characters) to the headwords
+//! containing it.
+//!
+//! A pattern might match a great part of a large index, so each search is bounded by
+//! [MatchOptions](struct.MatchOptions.html): a maximum number of results and a time limit after
+//! which the search is aborted with `DictError::Timeout`.
+
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::errors::DictError;
+
+/// default maximum number of headwords returned by a match
+pub const DEFAULT_MATCH_LIMIT: usize = 1000;
+/// default time limit for a single match
+pub const DEFAULT_MATCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// limit size of a compiled regular expression, so that a malicious pattern cannot request too
+/// much memory
+const MAX_REGEX_SIZE: usize = 1_048_576;
+
+/// check the time limit only every this many headwords, `Instant::now()` is not for free
+pub(crate) const TIMEOUT_CHECK_INTERVAL: usize = 256;
+
+/// Limits for a match.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchOptions {
+    /// maximum number of headwords to return; further matches are silently dropped
+    pub limit: usize,
+    /// time after which a search is aborted with `DictError::Timeout`
+    pub timeout: Duration,
+}
+
+impl Default for MatchOptions {
+    fn default() -> MatchOptions {
+        MatchOptions { limit: DEFAULT_MATCH_LIMIT, timeout: DEFAULT_MATCH_TIMEOUT }
+    }
+}
+
+/// Compile a regular expression in the syntax of the [regex crate](https://docs.rs/regex).
+///
+/// Most POSIX extended expressions, as used by dictd, are valid in this syntax, but they do not
+/// behave the same in every case: alternatives match leftmost-first instead of leftmost-longest
+/// and bracket classes such as `[[:alpha:]]` only match ASCII characters (use `\p{Alphabetic}`
+/// for any letter). Like `regexec(3)`, the expression is not anchored, use `^` and `$` to match
+/// whole headwords.
+pub fn compile_regex(pattern: &str) -> Result<Regex, DictError> {
+    RegexBuilder::new(pattern)
+        .size_limit(MAX_REGEX_SIZE)
+        .build()
+        .map_err(|e| DictError::InvalidPattern(e.to_string()))
+}
+
+/// Compile a glob-style pattern into a regular expression.
+///
+/// `*` matches any sequence of characters, `?` a single character and `[...]` a set of
+/// characters, negated with a leading `!` or `^`. A backslash makes the next character lose its
+/// special meaning. In contrast to regular expressions, a glob pattern always has to match the
+/// whole headword.
+pub fn compile_glob(pattern: &str) -> Result<Regex, DictError> {
+    let mut regex = String::with_capacity(pattern.len() + 8);
+    regex.push('^');
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                let mut class = String::from("[");
+                let mut closed = false;
+                let mut negated = false;
+                let mut members = 0;
+                for ch in chars.by_ref() {
+                    match ch {
+                        '!' | '^' if members == 0 && !negated => {
+                            negated = true;
+                            class.push('^');
+                            continue;
+                        },
+                        ']' if members > 0 => {
+                            closed = true;
+                            break;
+                        },
+                        // characters with a special meaning within regex classes
+                        '\\' | '[' | ']' | '&' | '~' => {
+                            class.push('\\');
+                            class.push(ch);
+                        },
+                        _ => class.push(ch),
+                    }
+                    members += 1;
+                }
+                if !closed {
+                    return Err(DictError::InvalidPattern(format!("unclosed character \
+                            class in glob pattern {}", pattern)));
+                }
+                class.push(']');
+                regex.push_str(&class);
+            },
+            '\\' => match chars.next() {
+                Some(escaped) => regex.push_str(&regex::escape(&escaped.to_string())),
+                None => regex.push_str(r"\\"),
+            },
+            _ => regex.push_str(&regex::escape(ch.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    compile_regex(&regex)
+}
+
+/// Select all headwords for which `predicate` holds.
+///
+/// Headwords are tested in the given order and the search stops as soon as `options.limit`
+/// matches have been found. If the search takes longer than `options.timeout`,
+/// `DictError::Timeout` is returned.
+pub fn filter_headwords<'a, I, F>(headwords: I, options: &MatchOptions, predicate: F)
+        -> Result<Vec<String>, DictError>
+        where I: IntoIterator<Item=&'a str>, F: Fn(&str) -> bool {
+    let start = Instant::now();
+    let mut matches = Vec::new();
+    for (count, word) in headwords.into_iter().enumerate() {
+        if matches.len() >= options.limit {
+            break;
+        }
+        if count % TIMEOUT_CHECK_INTERVAL == 0 && start.elapsed() > options.timeout {
+            return Err(DictError::Timeout);
+        }
+        if predicate(word) {
+            matches.push(word.to_string());
+        }
+    }
+    Ok(matches)
+}
+
+/// Compute the Levenshtein distance between two words.
+///
+/// The distance is the number of characters which need to be inserted, deleted or replaced to
+/// turn one word into the other.
+pub fn levenshtein_distance(first: &str, second: &str) -> usize {
+    let second: Vec<char> = second.chars().collect();
+    // distances between the prefix of `first` processed so far and all prefixes of `second`
+    let mut row: Vec<usize> = (0..=second.len()).collect();
+    for (i, a) in first.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in second.iter().enumerate() {
+            let substitution = diagonal + if a == *b { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[second.len()]
+}
+
+/// Compute the Soundex code of a word.
+///
+/// The code consists of the first letter of the word, followed by three digits representing the
+/// following consonants, e.g. "R163" for "Robert". Characters other than ASCII letters are
+/// ignored. `None` is returned for words without any ASCII letter.
+pub fn soundex(word: &str) -> Option<String> {
+    fn digit(ch: char) -> Option<char> {
+        match ch {
+            'b' | 'f' | 'p' | 'v' => Some('1'),
+            'c' | 'g' | 'j' | 'k' | 'q' | 's' | 'x' | 'z' => Some('2'),
+            'd' | 't' => Some('3'),
+            'l' => Some('4'),
+            'm' | 'n' => Some('5'),
+            'r' => Some('6'),
+            _ => None, // vowels, h, w and y
+        }
+    }
+
+    let mut letters = word.chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| c.to_ascii_lowercase());
+    let first = letters.next()?;
+    let mut code = first.to_ascii_uppercase().to_string();
+    let mut previous = digit(first);
+    for ch in letters {
+        let current = digit(ch);
+        if let Some(d) = current.filter(|_| current != previous) {
+            code.push(d);
+            if code.len() == 4 {
+                break;
+            }
+        }
+        // h and w do not separate consonants with the same code, vowels do
+        if ch != 'h' && ch != 'w' {
+            previous = current;
+        }
+    }
+    while code.len() < 4 {
+        code.push('0');
+    }
+    Some(code)
+}
+
+/// number of characters in an n-gram of the substring index
+const NGRAM_LENGTH: usize = 3;
+
+/// Auxiliary index over headwords for suffix and substring matches.
+///
+/// Headwords are identified by their position within the sorted list of headwords, so results
+/// can be returned in sorted order without sorting strings again.
+#[derive(Debug)]
+pub struct HeadwordIndex {
+    /// all headwords, sorted
+    headwords: Vec<String>,
+    /// reversed headwords with their id, sorted by the reversed spelling
+    reversed: Vec<(String, u32)>,
+    /// trigram &rarr; ids of all headwords containing the trigram, ascending
+    ngrams: HashMap<String, Vec<u32>>,
+    /// ids of headwords too short to contain a trigram
+    short: Vec<u32>,
+}
+
+impl HeadwordIndex {
+    /// Build the index from the given headwords.
+    pub fn new<'a, I: IntoIterator<Item=&'a str>>(headwords: I) -> HeadwordIndex {
+        let mut headwords: Vec<String> = headwords.into_iter().map(String::from).collect();
+        headwords.sort_unstable();
+        headwords.dedup();
+
+        let mut reversed: Vec<(String, u32)> = headwords.iter().enumerate()
+                .map(|(id, word)| (word.chars().rev().collect(), id as u32))
+                .collect();
+        reversed.sort_unstable();
+
+        let mut ngrams: HashMap<String, Vec<u32>> = HashMap::new();
+        let mut short = Vec::new();
+        for (id, word) in headwords.iter().enumerate() {
+            let chars: Vec<char> = word.chars().collect();
+            if chars.len() < NGRAM_LENGTH {
+                short.push(id as u32);
+                continue;
+            }
+            for window in chars.windows(NGRAM_LENGTH) {
+                let postings = ngrams.entry(window.iter().collect()).or_default();
+                // ids are visited in ascending order, so only the last one can be a duplicate
+                if postings.last() != Some(&(id as u32)) {
+                    postings.push(id as u32);
+                }
+            }
+        }
+        HeadwordIndex { headwords, reversed, ngrams, short }
+    }
+
+    /// All headwords in sorted order.
+    pub fn headwords(&self) -> &[String] {
+        &self.headwords
+    }
+
+    /// Check whether the given headword is contained.
+    pub fn contains(&self, word: &str) -> bool {
+        self.headwords.binary_search_by(|w| w.as_str().cmp(word)).is_ok()
+    }
+
+    /// All headwords starting with `prefix`, in sorted order.
+    pub fn with_prefix(&self, prefix: &str) -> &[String] {
+        let start = self.headwords.partition_point(|w| w.as_str() < prefix);
+        let length = self.headwords[start..].iter()
+                .take_while(|w| w.starts_with(prefix))
+                .count();
+        &self.headwords[start..start + length]
+    }
+
+    /// Find all headwords ending on `suffix`, in sorted order.
+    pub fn with_suffix(&self, suffix: &str) -> Vec<&str> {
+        let reversed_suffix: String = suffix.chars().rev().collect();
+        let start = self.reversed.partition_point(|(word, _)|
+                word.as_str() < reversed_suffix.as_str());
+        let mut ids: Vec<u32> = self.reversed[start..].iter()
+                .take_while(|(word, _)| word.starts_with(&reversed_suffix))
+                .map(|&(_, id)| id)
+                .collect();
+        ids.sort_unstable();
+        self.resolve(ids)
+    }
+
+    /// Find all headwords which might contain `substring`, in sorted order.
+    ///
+    /// The candidates are determined with the trigram index and need to be verified by the
+    /// caller, since a headword containing all trigrams of the query does not necessarily contain
+    /// the query itself.
+    pub fn substring_candidates(&self, substring: &str) -> Vec<&str> {
+        let chars: Vec<char> = substring.chars().collect();
+        if chars.is_empty() {
+            return self.headwords.iter().map(|w| w.as_str()).collect();
+        }
+        let mut ids = if chars.len() < NGRAM_LENGTH {
+            // all trigrams containing the query, together with all headwords too short for a
+            // trigram
+            let mut ids = self.short.clone();
+            for (ngram, postings) in self.ngrams.iter() {
+                if ngram.contains(substring) {
+                    ids.extend_from_slice(postings);
+                }
+            }
+            ids.sort_unstable();
+            ids.dedup();
+            ids
+        } else {
+            let mut postings: Vec<&Vec<u32>> = Vec::new();
+            for window in chars.windows(NGRAM_LENGTH) {
+                match self.ngrams.get(&window.iter().collect::<String>()) {
+                    Some(p) => postings.push(p),
+                    None => return Vec::new(),
+                }
+            }
+            // intersect, starting with the rarest trigram
+            postings.sort_unstable_by_key(|p| p.len());
+            let mut ids = postings[0].clone();
+            for other in &postings[1..] {
+                ids.retain(|id| other.binary_search(id).is_ok());
+            }
+            ids
+        };
+        ids.dedup();
+        self.resolve(ids)
+    }
+
+    fn resolve(&self, ids: Vec<u32>) -> Vec<&str> {
+        ids.into_iter().map(|id| self.headwords[id as usize].as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_special_characters_are_translated() {
+        let glob = compile_glob("a?o*").unwrap();
+        assert!(glob.is_match("amo"));
+        assert!(glob.is_match("amor"));
+        assert!(!glob.is_match("mamo"));
+    }
+
+    #[test]
+    fn test_glob_character_classes_and_escapes_work() {
+        let glob = compile_glob("[!b]o[cd]\\*.").unwrap();
+        assert!(glob.is_match("aoc*."));
+        assert!(!glob.is_match("boc*."));
+        assert!(!glob.is_match("aocx."));
+        assert!(!glob.is_match("aoc*x"));
+    }
+
+    #[test]
+    fn test_unclosed_glob_class_is_an_error() {
+        assert!(compile_glob("[abc").is_err());
+    }
+
+    #[test]
+    fn test_levenshtein_distance_counts_edits() {
+        assert_eq!(levenshtein_distance("amo", "amo"), 0);
+        assert_eq!(levenshtein_distance("amo", "amor"), 1);
+        assert_eq!(levenshtein_distance("amo", "ama"), 1);
+        assert_eq!(levenshtein_distance("amo", "mo"), 1);
+        assert_eq!(levenshtein_distance("kitten", "sitting"), 3);
+        assert_eq!(levenshtein_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_soundex_codes() {
+        assert_eq!(soundex("Robert").unwrap(), "R163");
+        assert_eq!(soundex("Rupert").unwrap(), "R163");
+        assert_eq!(soundex("Tymczak").unwrap(), "T522");
+        assert_eq!(soundex("Ashcraft").unwrap(), "A261");
+        assert_eq!(soundex("Pfister").unwrap(), "P236");
+        assert_eq!(soundex("a").unwrap(), "A000");
+        assert_eq!(soundex("123"), None);
+    }
+
+    #[test]
+    fn test_short_substrings_find_short_headwords() {
+        let index = HeadwordIndex::new(vec!["a", "ab", "cab", "xyz"]);
+        let candidates = index.substring_candidates("ab");
+        assert!(candidates.contains(&"ab"));
+        assert!(candidates.contains(&"cab"));
+        assert!(!candidates.contains(&"xyz"));
+    }
+}
@@ -0,0 +1,96 @@
+//! Metadata of a database from its `00-database-*` entries.
+//!
+//! `dictfmt` stores information about a database in entries with reserved headwords, e.g. the
+//! short description in `00-database-short` or the encoding in `00-database-utf8`. Depending on
+//! the options given to `dictfmt`, these headwords are written with or without dashes
+//! (`00databaseshort`). Some definitions repeat the headword in their first line, which is not
+//! part of the value.
+
+/// meta entry with the short description of the database
+pub const SHORT: &str = "00-database-short";
+/// meta entry with the long description of the database
+pub const INFO: &str = "00-database-info";
+/// meta entry with the URL the database was obtained from
+pub const URL: &str = "00-database-url";
+/// meta entry marking a database encoded in UTF-8
+pub const UTF8: &str = "00-database-utf8";
+/// meta entry marking a database using an 8-bit encoding
+pub const EIGHT_BIT: &str = "00-database-8bit";
+/// meta entry listing all characters used in headwords
+pub const ALPHABET: &str = "00-database-alphabet";
+/// meta entry asking to keep all characters of headwords, see the
+/// [collation](../collation/index.html) module
+pub const ALLCHARS: &str = "00-database-allchars";
+/// meta entry asking to compare headwords case-sensitively
+pub const CASE_SENSITIVE: &str = "00-database-case-sensitive";
+/// prefix of the meta entry with the version of `dictfmt`, e.g. `00-database-dictfmt-1.12.1`
+pub const DICTFMT_PREFIX: &str = "00-database-dictfmt-";
+
+/// Metadata of a database.
+///
+/// Flags like `utf8` are set if the corresponding entry exists, regardless of its content.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct DatabaseInfo {
+    /// short description, e.g. "Latin - German FreeDict dictionary ver. 0.4"
+    pub short_name: Option<String>,
+    /// long description, e.g. with authors and licence
+    pub info: Option<String>,
+    /// URL of the database
+    pub url: Option<String>,
+    /// definitions are encoded in UTF-8
+    pub utf8: bool,
+    /// definitions use an 8-bit encoding
+    pub eight_bit: bool,
+    /// characters used in headwords
+    pub alphabet: Option<String>,
+    /// headwords keep all characters
+    pub allchars: bool,
+    /// headwords are case-sensitive
+    pub case_sensitive: bool,
+    /// version of `dictfmt` which created the database, e.g. "1.12.1"
+    pub dictfmt_version: Option<String>,
+}
+
+/// Remove the headword of a meta entry from the start of its definition.
+///
+/// `name` is given with dashes, e.g. `00-database-short`; the headword is removed if it is present
+/// with or without dashes.
+pub fn strip_meta_headword<'a>(definition: &'a str, name: &str) -> &'a str {
+    if let Some(rest) = definition.strip_prefix(name) {
+        return rest;
+    }
+    let undashed: String = name.chars().filter(|c| *c != '-').collect();
+    definition.strip_prefix(undashed.as_str()).unwrap_or(definition)
+}
+
+/// Get the value of a meta entry from its definition.
+///
+/// The headword and surrounding white space are removed. The alphabet may contain a space, so
+/// only line breaks are removed from it.
+pub fn meta_value(definition: &str, name: &str) -> String {
+    let value = strip_meta_headword(definition, name);
+    if name == ALPHABET {
+        value.trim_matches(|c| c == '\n' || c == '\r').to_string()
+    } else {
+        value.trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dashed_and_undashed_headwords_are_stripped() {
+        assert_eq!(meta_value("00-database-short\n  Latin - German\n", SHORT), "Latin - German");
+        assert_eq!(meta_value("00databaseshort Latin - German", SHORT), "Latin - German");
+        assert_eq!(meta_value("Latin - German\n", SHORT), "Latin - German");
+    }
+
+    #[test]
+    fn test_spaces_of_the_alphabet_are_kept() {
+        assert_eq!(meta_value(" abc\n", ALPHABET), " abc");
+        assert_eq!(meta_value("00-database-alphabet\n abc\n", ALPHABET), " abc");
+    }
+}
@@ -0,0 +1,90 @@
+//! Unicode normalisation of headwords and queries.
+//!
+//! The same word can be encoded in different ways in Unicode: "é" may be a single code point or
+//! an "e" followed by a combining acute accent. Index files and user input do not necessarily
+//! agree on the form, so both are brought into the same normalisation form. Optionally,
+//! diacritics can be removed altogether, so that a query for "amo" finds the headword "amō".
+//!
+//! The [Normalizer](struct.Normalizer.html) is applied before the rules from the
+//! [collation](../collation/index.html) module.
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode normalisation form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnicodeForm {
+    /// leave words as they are
+    Unchanged,
+    /// canonical composition, e.g. "e" + combining acute accent becomes "é"
+    Nfc,
+    /// compatibility composition, which additionally replaces ligatures, full-width forms, etc.
+    Nfkc,
+}
+
+/// Configuration for the Unicode normalisation of headwords and queries.
+///
+/// By default, words are converted to NFC and diacritics are kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Normalizer {
+    /// normalisation form to convert words to
+    pub form: UnicodeForm,
+    /// remove diacritics (combining marks), e.g. "amō" becomes "amo"
+    pub fold_diacritics: bool,
+}
+
+impl Default for Normalizer {
+    fn default() -> Normalizer {
+        Normalizer { form: UnicodeForm::Nfc, fold_diacritics: false }
+    }
+}
+
+impl Normalizer {
+    /// Normalise a headword or query.
+    pub fn normalize(&self, word: &str) -> String {
+        if self.fold_diacritics {
+            // decompose to separate base characters from their diacritics
+            let folded = match self.form {
+                UnicodeForm::Nfkc => word.nfkd().filter(|c| !is_combining_mark(*c)).collect(),
+                _ => word.nfd().filter(|c| !is_combining_mark(*c)).collect::<String>(),
+            };
+            match self.form {
+                UnicodeForm::Unchanged => folded,
+                _ => folded.nfc().collect(),
+            }
+        } else {
+            match self.form {
+                UnicodeForm::Unchanged => word.to_string(),
+                UnicodeForm::Nfc => word.nfc().collect(),
+                UnicodeForm::Nfkc => word.nfkc().collect(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decomposed_words_are_composed() {
+        let normalizer = Normalizer::default();
+        assert_eq!(normalizer.normalize("e\u{301}vidence"), "\u{e9}vidence");
+        assert_eq!(normalizer.normalize("\u{e9}vidence"), "\u{e9}vidence");
+    }
+
+    #[test]
+    fn test_compatibility_forms_are_replaced_with_nfkc() {
+        let normalizer = Normalizer { form: UnicodeForm::Nfkc, fold_diacritics: false };
+        assert_eq!(normalizer.normalize("\u{fb01}nis"), "finis");
+        assert_eq!(Normalizer::default().normalize("\u{fb01}nis"), "\u{fb01}nis");
+    }
+
+    #[test]
+    fn test_diacritics_are_folded() {
+        let normalizer = Normalizer { form: UnicodeForm::Nfc, fold_diacritics: true };
+        assert_eq!(normalizer.normalize("am\u{14d}"), "amo");
+        assert_eq!(normalizer.normalize("e\u{301}vidence"), "evidence");
+        assert_eq!(normalizer.normalize("stra\u{df}e"), "stra\u{df}e");
+    }
+}
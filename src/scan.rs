@@ -0,0 +1,115 @@
+//! Find the databases in a directory.
+//!
+//! dictd databases are usually installed into one directory, e.g. `/usr/share/dictd`, each as a
+//! pair of files sharing a base name: `freedict-lat-deu.index` and `freedict-lat-deu.dict.dz`
+//! (or an uncompressed `freedict-lat-deu.dict`). [scan_directory](fn.scan_directory.html) pairs
+//! these files and loads each database into a
+//! [DictionaryCollection](../collection/struct.DictionaryCollection.html), named by its base name.
+//! Files without their counterpart and databases which could not be loaded are reported, but do
+//! not stop the scan. [scan_directory_lazy](fn.scan_directory_lazy.html) only pairs the files and
+//! opens each database on first use; databases which could not be opened are then listed by the
+//! [failures](../collection/struct.DictionaryCollection.html#method.failures) of the collection.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::collection::DictionaryCollection;
+use crate::errors::DictError;
+
+/// file name extension of index files
+const INDEX_EXTENSION: &str = ".index";
+/// file name extensions of dict files, in order of preference
+const DICT_EXTENSIONS: [&str; 2] = [".dict.dz", ".dict"];
+
+/// Result of scanning a directory.
+#[derive(Default)]
+pub struct ScanReport {
+    /// the databases found, named by their base name and sorted by it
+    pub collection: DictionaryCollection,
+    /// index files without a dict file and dict files without an index
+    pub orphans: Vec<PathBuf>,
+    /// databases which could not be loaded, given by their index file, with the error
+    ///
+    /// A lazy scan doesn't load the databases, so this is always empty; see the
+    /// [failures](../collection/struct.DictionaryCollection.html#method.failures) of the
+    /// collection instead.
+    pub errors: Vec<(PathBuf, DictError)>,
+}
+
+/// The files of a database.
+#[derive(Default)]
+struct Files {
+    index: Option<PathBuf>,
+    /// dict files with the position of their extension in `DICT_EXTENSIONS`
+    dicts: Vec<(usize, PathBuf)>,
+}
+
+/// Load all databases in a directory.
+///
+/// Each `*.index` file is paired with the `*.dict.dz` or, if there is none, the `*.dict` file of
+/// the same base name. Subdirectories and other files are ignored, as are files whose names are
+/// not valid UTF-8. Only errors reading the directory itself are returned as error.
+pub fn scan_directory<P: AsRef<Path>>(directory: P) -> Result<ScanReport, DictError> {
+    scan(directory.as_ref(), false)
+}
+
+/// Find all databases in a directory, opening each on first use.
+///
+/// Files are paired like with [scan_directory](fn.scan_directory.html), but the databases are not
+/// loaded, so errors in their files are only found by the first lookup needing them. Lookups in
+/// `*` and `!` skip such databases; they are listed by the
+/// [failures](../collection/struct.DictionaryCollection.html#method.failures) of the collection.
+pub fn scan_directory_lazy<P: AsRef<Path>>(directory: P) -> Result<ScanReport, DictError> {
+    scan(directory.as_ref(), true)
+}
+
+fn scan(directory: &Path, lazy: bool) -> Result<ScanReport, DictError> {
+    let mut databases: BTreeMap<String, Files> = BTreeMap::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let file_name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(file_name) => file_name.to_string(),
+            None => continue,
+        };
+        if let Some(name) = file_name.strip_suffix(INDEX_EXTENSION) {
+            databases.entry(name.to_string()).or_default().index = Some(path);
+        } else if let Some((preference, name)) = DICT_EXTENSIONS.iter().enumerate()
+                .find_map(|(i, extension)| file_name.strip_suffix(extension).map(|n| (i, n))) {
+            databases.entry(name.to_string()).or_default().dicts.push((preference, path));
+        }
+    }
+
+    let mut report = ScanReport::default();
+    for (name, mut files) in databases {
+        files.dicts.sort();
+        let (index_path, dict_path) = match files.index {
+            Some(index) => match files.dicts.into_iter().next() {
+                Some((_, dict)) => (index, dict),
+                None => {
+                    report.orphans.push(index);
+                    continue;
+                },
+            },
+            None => {
+                report.orphans.extend(files.dicts.into_iter().map(|(_, dict)| dict));
+                continue;
+            },
+        };
+        if lazy {
+            report.collection.insert_lazy(name, move ||
+                    crate::load_dictionary_from_file(&dict_path, &index_path));
+        } else {
+            match crate::load_dictionary_from_file(&dict_path, &index_path) {
+                Ok(dictionary) => {
+                    report.collection.insert(name, dictionary);
+                },
+                Err(e) => report.errors.push((index_path, e)),
+            }
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,447 @@
+//! Read dictionaries in the StarDict format.
+//!
+//! A StarDict dictionary consists of several files sharing a base name:
+//!
+//! -   `.ifo`: metadata as `key=value` lines, e.g. the name of the book and the number of words
+//! -   `.idx` (optionally gzipped as `.idx.gz`): the headwords, each followed by the offset and
+//!     size of its definition as big-endian numbers. The offset has 64 bits if the `.ifo`
+//!     requests `idxoffsetbits=64`, otherwise 32 bits.
+//! -   `.syn` (optional): synonyms, each followed by the number of an entry in the `.idx` file
+//! -   `.dict` or `.dict.dz`: the definitions; `.dict.dz` files are dictzipped like the ones of
+//!     dictd.
+//!
+//! A definition is made up of fields, each with a type: lowercase types (e.g. `m` for plain text,
+//! `h` for HTML) are text, uppercase types (e.g. `P` for pictures) are binary data. If the `.ifo`
+//! declares a `sametypesequence`, the type markers are omitted from the definitions. Only text
+//! fields are returned from lookups, separated by line breaks.
+//!
+//! [load_stardict](fn.load_stardict.html) creates a [Dictionary](../struct.Dictionary.html) with
+//! the metadata from the `.ifo` file, see
+//! [Dictionary::info](../struct.Dictionary.html#method.info).
+//! [export_stardict](fn.export_stardict.html) writes any dictionary in the StarDict format.
+
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
+use flate2::read::GzDecoder;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::dictreader::{self, DictReader};
+use crate::dictwriter;
+use crate::errors::DictError;
+use crate::indexing::{Index, Location};
+use crate::metadata::DatabaseInfo;
+use crate::Dictionary;
+
+/// first line of every `.ifo` file
+const IFO_MAGIC: &str = "StarDict's dict ifo file";
+
+/// Metadata from a StarDict `.ifo` file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct StarDictInfo {
+    /// version of the format, e.g. "2.4.2" or "3.0.0"
+    pub version: String,
+    /// name of the dictionary
+    pub bookname: String,
+    /// number of entries in the `.idx` file
+    pub word_count: u64,
+    /// number of entries in the `.syn` file
+    pub syn_word_count: u64,
+    /// size of the (uncompressed) `.idx` file
+    pub idx_file_size: u64,
+    /// number of bits of the offsets in the `.idx` file, 32 or 64
+    pub idx_offset_bits: u32,
+    /// types of the fields of each definition, if the same for all definitions
+    pub same_type_sequence: Option<String>,
+    /// author of the dictionary
+    pub author: Option<String>,
+    /// e-mail address of the author
+    pub email: Option<String>,
+    /// website of the dictionary
+    pub website: Option<String>,
+    /// description of the dictionary
+    pub description: Option<String>,
+    /// date of creation
+    pub date: Option<String>,
+}
+
+impl StarDictInfo {
+    /// Convert to the metadata of a dict database.
+    ///
+    /// The book name becomes the short name; description, author, e-mail address and date make
+    /// up the long description. StarDict dictionaries are always encoded in UTF-8.
+    pub fn to_database_info(&self) -> DatabaseInfo {
+        let mut info = self.description.clone().unwrap_or_default()
+            .replace("<br>", "\n");
+        for (label, value) in [("Author", &self.author), ("E-mail", &self.email),
+                ("Date", &self.date)].iter() {
+            if let Some(value) = value {
+                info.push_str(&format!("\n{}: {}", label, value));
+            }
+        }
+        DatabaseInfo {
+            short_name: Some(self.bookname.clone()),
+            info: Some(info.trim().to_string()).filter(|i| !i.is_empty()),
+            url: self.website.clone(),
+            utf8: true,
+            ..DatabaseInfo::default()
+        }
+    }
+}
+
+/// Parse a `.ifo` file.
+pub fn parse_ifo<B: BufRead>(reader: B) -> Result<StarDictInfo, DictError> {
+    let mut lines = reader.lines();
+    let first_line = lines.next().transpose()?.unwrap_or_default();
+    if first_line.trim_start_matches('\u{feff}').trim_end() != IFO_MAGIC {
+        return Err(DictError::InvalidFileFormat("not a StarDict .ifo file".into(), None));
+    }
+    let mut info = StarDictInfo { idx_offset_bits: 32, ..StarDictInfo::default() };
+    for line in lines {
+        let line = line?;
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim_end_matches('\r').to_string()),
+            None => continue,
+        };
+        let number = |value: &str| value.trim().parse::<u64>().map_err(|_|
+                DictError::InvalidFileFormat(format!("invalid number for {} in .ifo file: {}",
+                        key, value), None));
+        match key {
+            "version" => info.version = value,
+            "bookname" => info.bookname = value,
+            "wordcount" => info.word_count = number(&value)?,
+            "synwordcount" => info.syn_word_count = number(&value)?,
+            "idxfilesize" => info.idx_file_size = number(&value)?,
+            "idxoffsetbits" => info.idx_offset_bits = match number(&value)? {
+                32 => 32,
+                64 => 64,
+                bits => return Err(DictError::InvalidFileFormat(format!("unsupported \
+                        idxoffsetbits {}", bits), None)),
+            },
+            "sametypesequence" => info.same_type_sequence = Some(value),
+            "author" => info.author = Some(value),
+            "email" => info.email = Some(value),
+            "website" => info.website = Some(value),
+            "description" => info.description = Some(value),
+            "date" => info.date = Some(value),
+            _ => (),
+        }
+    }
+    Ok(info)
+}
+
+/// Parse a `.idx` file into headwords with the offset and size of their definition.
+pub fn parse_idx<R: Read>(reader: R, offset_bits: u32)
+        -> Result<Vec<(String, u64, u64)>, DictError> {
+    let mut reader = BufReader::new(reader);
+    let mut entries = Vec::new();
+    while let Some(word) = read_word(&mut reader)? {
+        let offset = match offset_bits {
+            64 => reader.read_u64::<BigEndian>()?,
+            _ => u64::from(reader.read_u32::<BigEndian>()?),
+        };
+        let size = u64::from(reader.read_u32::<BigEndian>()?);
+        entries.push((word, offset, size));
+    }
+    Ok(entries)
+}
+
+/// Parse a `.syn` file into synonyms with the number of their entry in the `.idx` file.
+pub fn parse_syn<R: Read>(reader: R) -> Result<Vec<(String, u32)>, DictError> {
+    let mut reader = BufReader::new(reader);
+    let mut synonyms = Vec::new();
+    while let Some(word) = read_word(&mut reader)? {
+        synonyms.push((word, reader.read_u32::<BigEndian>()?));
+    }
+    Ok(synonyms)
+}
+
+/// Read a NUL-terminated word, `None` at the end of the file.
+fn read_word<B: BufRead>(reader: &mut B) -> Result<Option<String>, DictError> {
+    let mut word = Vec::new();
+    if reader.read_until(b'\0', &mut word)? == 0 {
+        return Ok(None);
+    }
+    if word.pop() != Some(b'\0') {
+        return Err(DictError::IoError(io::Error::new(io::ErrorKind::UnexpectedEof,
+                "headword is not terminated")));
+    }
+    Ok(Some(String::from_utf8(word)?))
+}
+
+/// Load a StarDict dictionary, given the path of its `.ifo` file.
+///
+/// The other files are searched next to it: `.idx` or `.idx.gz`, optionally `.syn`, and `.dict`
+/// or `.dict.dz`. Synonyms are added to the index with the location of their entry.
+pub fn load_stardict<P: AsRef<Path>>(ifo_path: P) -> Result<Dictionary, DictError> {
+    let ifo_path = ifo_path.as_ref();
+    let info = parse_ifo(BufReader::new(File::open(ifo_path)?))?;
+    let with_extension = |extension: &str| -> PathBuf {
+        let mut path = ifo_path.with_extension("").into_os_string();
+        path.push(extension);
+        PathBuf::from(path)
+    };
+    let existing = |extensions: &[&str]| extensions.iter().map(|e| with_extension(e))
+        .find(|p| p.exists());
+
+    let idx_path = existing(&[".idx", ".idx.gz"]).ok_or_else(|| missing_file(ifo_path, ".idx"))?;
+    let idx_file = File::open(&idx_path)?;
+    let entries = if idx_path.extension().is_some_and(|e| e == "gz") {
+        parse_idx(GzDecoder::new(idx_file), info.idx_offset_bits)?
+    } else {
+        parse_idx(idx_file, info.idx_offset_bits)?
+    };
+    let synonyms = match existing(&[".syn"]) {
+        Some(path) => parse_syn(File::open(path)?)?,
+        None => Vec::new(),
+    };
+    let dict_path = existing(&[".dict.dz", ".dict"])
+        .ok_or_else(|| missing_file(ifo_path, ".dict"))?;
+    let reader = StarDictReader { data: dictreader::load_dict(dict_path)?,
+            same_type_sequence: info.same_type_sequence.clone() };
+
+    let index = build_index(&entries, &synonyms)?;
+    let mut dictionary = crate::load_dictionary(Box::new(reader), index);
+    dictionary.set_info(info.to_database_info());
+    Ok(dictionary)
+}
+
+/// Build an index from the entries of a `.idx` file and the synonyms of a `.syn` file.
+pub fn build_index(entries: &[(String, u64, u64)], synonyms: &[(String, u32)])
+        -> Result<Index, DictError> {
+    let mut index = Index::with_capacity(entries.len() + synonyms.len());
+    for (word, offset, size) in entries {
+        index.entry(word.clone()).or_default().push(Location::new(*offset, *size));
+    }
+    for (synonym, number) in synonyms {
+        let (_, offset, size) = entries.get(*number as usize).ok_or_else(||
+                DictError::InvalidFileFormat(format!("synonym {} refers to unknown entry {}",
+                        synonym, number), None))?;
+        index.entry(synonym.clone()).or_default().push(Location::new(*offset, *size));
+    }
+    Ok(index)
+}
+
+/// Compare headwords the way StarDict sorts its `.idx` and `.syn` files.
+///
+/// Headwords are compared byte-wise with ASCII letters folded to lowercase, like
+/// `g_ascii_strcasecmp`; ties are broken by comparing the plain bytes, like `strcmp`.
+pub fn compare_headwords(a: &str, b: &str) -> Ordering {
+    let folded = |s: &str| s.bytes().map(|b| b.to_ascii_lowercase()).collect::<Vec<u8>>();
+    folded(a).cmp(&folded(b)).then_with(|| a.as_bytes().cmp(b.as_bytes()))
+}
+
+/// Export a dictionary to the StarDict format.
+///
+/// `ifo_path` is the path of the `.ifo` file; the `.idx`, `.syn` and `.dict.dz` files are written
+/// next to it. Meta entries are not exported, the metadata of the dictionary becomes the book name,
+/// description and website of the `.ifo` file. Headwords sharing a definition are written once to
+/// the `.dict.dz` file: the headword the definition starts with (or else the first one) goes to
+/// the `.idx` file, the others become synonyms in the `.syn` file. Definitions are stored as plain
+/// text (`sametypesequence=m`).
+pub fn export_stardict<P: AsRef<Path>>(dictionary: &mut Dictionary, ifo_path: P)
+        -> Result<(), DictError> {
+    let ifo_path = ifo_path.as_ref();
+    let info = dictionary.info()?;
+
+    // group headwords of the same definition, these are yielded consecutively
+    let mut groups: Vec<((u64, u64), Vec<String>, String)> = Vec::new();
+    let mut iter = dictionary.entries();
+    while let Some(entry) = iter.next() {
+        let (headword, definition) = entry?;
+        check_headword(&headword)?;
+        let span = iter.last_span().unwrap_or_default();
+        match groups.last_mut() {
+            Some((last, headwords, _)) if *last == span => headwords.push(headword),
+            _ => groups.push((span, vec![headword], definition)),
+        }
+    }
+
+    let mut data = Vec::new();
+    let mut entries = Vec::with_capacity(groups.len());
+    let mut synonyms = Vec::new();
+    for (_, mut headwords, definition) in groups {
+        let headword = headwords.remove(crate::main_headword(&headwords, &definition));
+        let entry = entries.len();
+        synonyms.extend(headwords.into_iter().map(|synonym| (synonym, entry)));
+        entries.push((headword, data.len() as u64, definition.len() as u64));
+        data.extend_from_slice(definition.as_bytes());
+    }
+
+    // sort the entries and renumber the synonyms accordingly
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by(|a, b| compare_headwords(&entries[*a].0, &entries[*b].0)
+            .then_with(|| entries[*a].1.cmp(&entries[*b].1)));
+    let mut numbers = vec![0u32; entries.len()];
+    for (number, entry) in order.iter().enumerate() {
+        numbers[*entry] = number as u32;
+    }
+    synonyms.sort_by(|a, b| compare_headwords(&a.0, &b.0)
+            .then_with(|| numbers[a.1].cmp(&numbers[b.1])));
+
+    // compress first: a dictzip file holds less than 4 GiB, so 32-bit offsets always suffice
+    let mut compressed = Vec::new();
+    dictwriter::write_dictzip(&data, &mut compressed)?;
+    let mut idx = Vec::new();
+    for entry in &order {
+        let (ref headword, offset, size) = entries[*entry];
+        idx.extend_from_slice(headword.as_bytes());
+        idx.push(0);
+        idx.write_u32::<BigEndian>(offset as u32)?;
+        idx.write_u32::<BigEndian>(size as u32)?;
+    }
+    let mut syn = Vec::new();
+    for (synonym, entry) in &synonyms {
+        syn.extend_from_slice(synonym.as_bytes());
+        syn.push(0);
+        syn.write_u32::<BigEndian>(numbers[*entry])?;
+    }
+
+    let with_extension = |extension: &str| -> PathBuf {
+        let mut path = ifo_path.with_extension("").into_os_string();
+        path.push(extension);
+        PathBuf::from(path)
+    };
+    let mut ifo = BufWriter::new(File::create(ifo_path)?);
+    writeln!(ifo, "{}", IFO_MAGIC)?;
+    writeln!(ifo, "version=2.4.2")?;
+    writeln!(ifo, "bookname={}", ifo_value(info.short_name.as_deref().unwrap_or("unknown")))?;
+    writeln!(ifo, "wordcount={}", entries.len())?;
+    if !synonyms.is_empty() {
+        writeln!(ifo, "synwordcount={}", synonyms.len())?;
+    }
+    writeln!(ifo, "idxfilesize={}", idx.len())?;
+    if let Some(ref url) = info.url {
+        writeln!(ifo, "website={}", ifo_value(url))?;
+    }
+    if let Some(ref description) = info.info {
+        writeln!(ifo, "description={}", ifo_value(description))?;
+    }
+    writeln!(ifo, "sametypesequence=m")?;
+    ifo.flush()?;
+
+    std::fs::write(with_extension(".idx"), idx)?;
+    if !synonyms.is_empty() {
+        std::fs::write(with_extension(".syn"), syn)?;
+    }
+    std::fs::write(with_extension(".dict.dz"), compressed)?;
+    Ok(())
+}
+
+/// Headwords are terminated by NUL in `.idx` and `.syn` files and must fit into 256 bytes.
+fn check_headword(headword: &str) -> Result<(), DictError> {
+    if let Some(position) = headword.find('\0') {
+        return Err(DictError::InvalidCharacter('\0', None, Some(position)));
+    }
+    if headword.is_empty() || headword.len() >= 256 {
+        return Err(DictError::InvalidFileFormat(format!("headword {:?} cannot be stored in \
+                a StarDict index", headword), None));
+    }
+    Ok(())
+}
+
+/// Values in `.ifo` files are single lines; line breaks are written as `<br>`.
+fn ifo_value(value: &str) -> String {
+    value.trim().replace("\r\n", "<br>").replace('\n', "<br>")
+}
+
+fn missing_file(ifo_path: &Path, extension: &str) -> DictError {
+    DictError::InvalidFileFormat(format!("no {} file found for this dictionary", extension),
+            Some(ifo_path.to_string_lossy().into_owned()))
+}
+
+/// Reader for the definitions of a StarDict dictionary, extracting the text fields.
+pub struct StarDictReader {
+    data: Box<dyn DictReader>,
+    same_type_sequence: Option<String>,
+}
+
+impl StarDictReader {
+    /// Wrap a reader of a `.dict` or `.dict.dz` file.
+    pub fn new(data: Box<dyn DictReader>, same_type_sequence: Option<String>) -> StarDictReader {
+        StarDictReader { data, same_type_sequence }
+    }
+}
+
+impl DictReader for StarDictReader {
+    fn fetch_definition(&mut self, start_offset: u64, length: u64) -> Result<String, DictError> {
+        // binary fields needn't be valid UTF-8, so only the text fields are decoded
+        let definition = self.data.fetch_bytes(start_offset, length)?;
+        let fields = match self.same_type_sequence {
+            Some(ref types) => split_typed_fields(&definition, types.as_bytes()),
+            None => split_marked_fields(&definition),
+        };
+        Ok(fields.join("\n"))
+    }
+
+    fn total_length(&self) -> Option<u64> {
+        self.data.total_length()
+    }
+}
+
+/// Split a definition whose fields have the given types, without type markers.
+///
+/// All but the last field are terminated by NUL (text) or prefixed by their size (binary).
+fn split_typed_fields(mut data: &[u8], types: &[u8]) -> Vec<String> {
+    let mut fields = Vec::new();
+    for (number, field_type) in types.iter().enumerate() {
+        let last = number + 1 == types.len();
+        let (field, rest) = if last {
+            (data, &data[data.len()..])
+        } else {
+            next_field(data, *field_type)
+        };
+        if field_type.is_ascii_lowercase() {
+            fields.push(String::from_utf8_lossy(field).into_owned());
+        }
+        data = rest;
+    }
+    fields
+}
+
+/// Split a definition whose fields are each preceded by their type.
+fn split_marked_fields(mut data: &[u8]) -> Vec<String> {
+    let mut fields = Vec::new();
+    while let Some((field_type, rest)) = data.split_first() {
+        let (field, rest) = next_field(rest, *field_type);
+        if field_type.is_ascii_lowercase() {
+            fields.push(String::from_utf8_lossy(field).into_owned());
+        }
+        data = rest;
+    }
+    fields
+}
+
+/// Split off a field which is terminated by NUL (text) or prefixed by its size (binary).
+fn next_field(data: &[u8], field_type: u8) -> (&[u8], &[u8]) {
+    if field_type.is_ascii_lowercase() {
+        match data.iter().position(|b| *b == 0) {
+            Some(end) => (&data[..end], &data[end + 1..]),
+            None => (data, &data[data.len()..]),
+        }
+    } else {
+        let size = data.get(..4).map_or(0, |s| BigEndian::read_u32(s) as usize);
+        let end = (4 + size).min(data.len());
+        (&data[4.min(data.len())..end], &data[end..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_with_same_type_sequence() {
+        assert_eq!(split_typed_fields(b"text", b"m"), vec!["text"]);
+        assert_eq!(split_typed_fields(b"/p/\0text", b"tm"), vec!["/p/", "text"]);
+        assert_eq!(split_typed_fields(b"\0\0\0\x02abtext", b"Pm"), vec!["text"]);
+    }
+
+    #[test]
+    fn test_fields_with_type_markers() {
+        assert_eq!(split_marked_fields(b"mtext\0h<b>x</b>\0"), vec!["text", "<b>x</b>"]);
+        assert_eq!(split_marked_fields(b"P\0\0\0\x01xmtext"), vec!["text"]);
+    }
+}
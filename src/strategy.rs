@@ -0,0 +1,298 @@
+//! Pluggable match strategies.
+//!
+//! The DICT protocol (RFC 2229) lets clients match headwords using a named strategy; the server
+//! lists the strategies it knows with `SHOW STRAT`. This module defines the
+//! [MatchStrategy](trait.MatchStrategy.html) trait, implementations for the strategies known from
+//! dictd and a [StrategyRegistry](struct.StrategyRegistry.html), which keeps the strategies
+//! available for a dictionary. Custom strategies, e.g. a lemmatiser, can be registered alongside
+//! the built-in ones.
+
+use crate::errors::DictError;
+#[cfg(feature = "fst")]
+use crate::fstindex::FstIndex;
+use crate::matching::{self, HeadwordIndex, MatchOptions};
+
+/// A named strategy to match headwords against a query.
+pub trait MatchStrategy {
+    /// Name of the strategy, as used in the DICT protocol, e.g. `prefix`.
+    fn name(&self) -> &str;
+
+    /// Short description of the strategy, as reported by `SHOW STRAT`.
+    fn description(&self) -> &str;
+
+    /// Whether the query is a pattern, which must not be altered.
+    ///
+    /// Other queries are normalised like the headwords of the dictionary (see the
+    /// [collation](../collation/index.html) module) before they are passed to
+    /// [find](#tymethod.find).
+    fn takes_pattern(&self) -> bool {
+        false
+    }
+
+    /// Find all headwords from `index` matching `query`.
+    ///
+    /// Matches should be returned in the order of the index; implementations are expected to
+    /// respect the limits given in `options`.
+    fn find(&self, query: &str, index: &HeadwordIndex, options: &MatchOptions)
+            -> Result<Vec<String>, DictError>;
+
+    /// Find all headwords from a compact `index` matching `query`.
+    ///
+    /// Dictionaries with a [compact index](../struct.Dictionary.html#method.compact_index) ask
+    /// this first, so that strategies which can search the transducer directly do not need a
+    /// [HeadwordIndex](../matching/struct.HeadwordIndex.html). The default returns `None`, then
+    /// [find](#tymethod.find) is used. This is only available with the `fst` feature.
+    #[cfg(feature = "fst")]
+    fn find_compact(&self, _query: &str, _index: &FstIndex, _options: &MatchOptions)
+            -> Option<Result<Vec<String>, DictError>> {
+        None
+    }
+}
+
+/// Match headwords exactly (`exact`).
+pub struct Exact;
+
+impl MatchStrategy for Exact {
+    fn name(&self) -> &str {
+        "exact"
+    }
+
+    fn description(&self) -> &str {
+        "Match headwords exactly"
+    }
+
+    fn find(&self, query: &str, index: &HeadwordIndex, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        let candidates = Some(query).filter(|q| index.contains(q));
+        matching::filter_headwords(candidates, options, |_| true)
+    }
+
+    #[cfg(feature = "fst")]
+    fn find_compact(&self, query: &str, index: &FstIndex, options: &MatchOptions)
+            -> Option<Result<Vec<String>, DictError>> {
+        let candidates = Some(query).filter(|q| index.contains(q));
+        Some(matching::filter_headwords(candidates, options, |_| true))
+    }
+}
+
+/// Match headwords starting with the query (`prefix`).
+pub struct Prefix;
+
+impl MatchStrategy for Prefix {
+    fn name(&self) -> &str {
+        "prefix"
+    }
+
+    fn description(&self) -> &str {
+        "Match prefixes"
+    }
+
+    fn find(&self, query: &str, index: &HeadwordIndex, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        let candidates = index.with_prefix(query).iter().map(|w| w.as_str());
+        matching::filter_headwords(candidates, options, |_| true)
+    }
+
+    #[cfg(feature = "fst")]
+    fn find_compact(&self, query: &str, index: &FstIndex, options: &MatchOptions)
+            -> Option<Result<Vec<String>, DictError>> {
+        Some(index.prefix_matches(query, options))
+    }
+}
+
+/// Match headwords ending on the query (`suffix`).
+pub struct Suffix;
+
+impl MatchStrategy for Suffix {
+    fn name(&self) -> &str {
+        "suffix"
+    }
+
+    fn description(&self) -> &str {
+        "Match suffixes"
+    }
+
+    fn find(&self, query: &str, index: &HeadwordIndex, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        matching::filter_headwords(index.with_suffix(query), options, |_| true)
+    }
+}
+
+/// Match headwords containing the query (`substring`).
+pub struct Substring;
+
+impl MatchStrategy for Substring {
+    fn name(&self) -> &str {
+        "substring"
+    }
+
+    fn description(&self) -> &str {
+        "Match substring occurring anywhere in a headword"
+    }
+
+    fn find(&self, query: &str, index: &HeadwordIndex, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        matching::filter_headwords(index.substring_candidates(query), options,
+                |w| w.contains(query))
+    }
+}
+
+/// Match headwords with a regular expression (`re`).
+///
+/// The syntax is the one of the regex crate, see
+/// [compile_regex](../matching/fn.compile_regex.html) for how it differs from dictd's POSIX
+/// regular expressions.
+pub struct RegularExpression;
+
+impl MatchStrategy for RegularExpression {
+    fn name(&self) -> &str {
+        "re"
+    }
+
+    fn description(&self) -> &str {
+        "Regular expressions (Rust regex syntax)"
+    }
+
+    fn takes_pattern(&self) -> bool {
+        true
+    }
+
+    fn find(&self, query: &str, index: &HeadwordIndex, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        let regex = matching::compile_regex(query)?;
+        let candidates = index.headwords().iter().map(|w| w.as_str());
+        matching::filter_headwords(candidates, options, |w| regex.is_match(w))
+    }
+}
+
+/// Match headwords with a glob-style pattern (`glob`).
+pub struct Glob;
+
+impl MatchStrategy for Glob {
+    fn name(&self) -> &str {
+        "glob"
+    }
+
+    fn description(&self) -> &str {
+        "Shell-style wildcards (*, ? and [...])"
+    }
+
+    fn takes_pattern(&self) -> bool {
+        true
+    }
+
+    fn find(&self, query: &str, index: &HeadwordIndex, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        let glob = matching::compile_glob(query)?;
+        let candidates = index.headwords().iter().map(|w| w.as_str());
+        matching::filter_headwords(candidates, options, |w| glob.is_match(w))
+    }
+}
+
+/// Match headwords within a Levenshtein distance of one (`lev`).
+pub struct Levenshtein;
+
+impl MatchStrategy for Levenshtein {
+    fn name(&self) -> &str {
+        "lev"
+    }
+
+    fn description(&self) -> &str {
+        "Match headwords within Levenshtein distance one"
+    }
+
+    fn find(&self, query: &str, index: &HeadwordIndex, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        let length = query.chars().count();
+        let candidates = index.headwords().iter().map(|w| w.as_str());
+        matching::filter_headwords(candidates, options, |w|
+                // cheap check first: the length may differ by one character at most
+                w.chars().count().abs_diff(length) <= 1
+                    && matching::levenshtein_distance(query, w) <= 1)
+    }
+
+    #[cfg(feature = "fst")]
+    fn find_compact(&self, query: &str, index: &FstIndex, options: &MatchOptions)
+            -> Option<Result<Vec<String>, DictError>> {
+        Some(index.fuzzy_matches(query, 1, options))
+    }
+}
+
+/// Match headwords sounding similar to the query (`soundex`).
+pub struct Soundex;
+
+impl MatchStrategy for Soundex {
+    fn name(&self) -> &str {
+        "soundex"
+    }
+
+    fn description(&self) -> &str {
+        "Match using SOUNDEX algorithm"
+    }
+
+    fn find(&self, query: &str, index: &HeadwordIndex, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        let code = match matching::soundex(query) {
+            Some(code) => code,
+            None => return Ok(Vec::new()),
+        };
+        let candidates = index.headwords().iter().map(|w| w.as_str());
+        matching::filter_headwords(candidates, options, |w|
+                matching::soundex(w).as_ref() == Some(&code))
+    }
+}
+
+/// The match strategies available for a dictionary.
+///
+/// The default registry contains all strategies from this module. Strategies are kept in the
+/// order of registration, which is also the order reported to clients.
+pub struct StrategyRegistry {
+    strategies: Vec<Box<dyn MatchStrategy>>,
+}
+
+impl StrategyRegistry {
+    /// Create a registry without any strategy.
+    pub fn empty() -> StrategyRegistry {
+        StrategyRegistry { strategies: Vec::new() }
+    }
+
+    /// Add a strategy.
+    ///
+    /// A previously registered strategy with the same name is replaced.
+    pub fn register(&mut self, strategy: Box<dyn MatchStrategy>) {
+        match self.strategies.iter().position(|s| s.name() == strategy.name()) {
+            Some(pos) => self.strategies[pos] = strategy,
+            None => self.strategies.push(strategy),
+        }
+    }
+
+    /// Get a strategy by its name.
+    pub fn get(&self, name: &str) -> Option<&dyn MatchStrategy> {
+        self.strategies.iter().find(|s| s.name() == name).map(|s| s.as_ref())
+    }
+
+    /// Iterate over all strategies.
+    pub fn iter(&self) -> impl Iterator<Item=&dyn MatchStrategy> {
+        self.strategies.iter().map(|s| s.as_ref())
+    }
+
+    /// List names and descriptions of all strategies, as required for `SHOW STRAT`.
+    pub fn list(&self) -> Vec<(&str, &str)> {
+        self.iter().map(|s| (s.name(), s.description())).collect()
+    }
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> StrategyRegistry {
+        let mut registry = StrategyRegistry::empty();
+        registry.register(Box::new(Exact));
+        registry.register(Box::new(Prefix));
+        registry.register(Box::new(Substring));
+        registry.register(Box::new(Suffix));
+        registry.register(Box::new(RegularExpression));
+        registry.register(Box::new(Glob));
+        registry.register(Box::new(Levenshtein));
+        registry.register(Box::new(Soundex));
+        registry
+    }
+}
@@ -0,0 +1,399 @@
+//! Convert FreeDict TEI dictionaries to the dict format.
+//!
+//! FreeDict dictionaries are written in [TEI](https://tei-c.org) XML. This module parses the
+//! `<entry>` elements of a TEI file and renders them into the plain-text layout of the FreeDict
+//! databases for dictd:
+//!
+//! ```text
+//! abdo (abdere, abdidi, abditum) <sg, v>
+//!  1. verbergen
+//!  2. verstecken
+//! ```
+//!
+//! The first line holds the headwords with their pronunciation, the inflected forms in
+//! parentheses and the grammar in angle brackets. Each sense follows on its own line with its
+//! translations, numbered if there is more than one. Definitions (`<def>`) are put in parentheses
+//! on a line before the translations, examples follow the translations and notes and cross
+//! references (`<note>`, `<xr>`) come last, with referenced headwords in curly braces.
+//!
+//! The converted entries are written with a
+//! [DictionaryBuilder](../builder/struct.DictionaryBuilder.html). This module is only available
+//! with the `tei` feature.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::builder::DictionaryBuilder;
+use crate::errors::DictError;
+use crate::metadata::DatabaseInfo;
+
+/// indentation of note lines
+const NOTE_INDENTATION: &str = "         ";
+/// indentation of example lines
+const EXAMPLE_INDENTATION: &str = "     ";
+/// maximum nesting depth of elements within an entry or the header
+const MAX_DEPTH: usize = 64;
+
+/// A dictionary entry from a TEI file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Entry {
+    /// headwords (`form/orth`)
+    pub headwords: Vec<String>,
+    /// pronunciations (`form/pron`)
+    pub pronunciations: Vec<String>,
+    /// inflected forms (`form[@type='infl']`)
+    pub inflections: Vec<Form>,
+    /// grammatical information about the headword, e.g. part of speech and gender
+    pub grammar: Vec<String>,
+    /// senses with their translations
+    pub senses: Vec<Sense>,
+    /// notes and cross references of the entry itself
+    pub notes: Vec<String>,
+}
+
+/// An inflected form of a headword.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Form {
+    /// spelling of the form
+    pub orth: String,
+    /// grammatical information about the form
+    pub grammar: Vec<String>,
+}
+
+/// A sense of a headword.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sense {
+    /// grammatical information and usage labels for this sense
+    pub grammar: Vec<String>,
+    /// definitions in the source language (`def`)
+    pub definitions: Vec<String>,
+    /// translations (`cit[@type='trans']/quote`)
+    pub translations: Vec<String>,
+    /// examples (`cit[@type='example']`)
+    pub examples: Vec<Example>,
+    /// notes and cross references
+    pub notes: Vec<String>,
+}
+
+/// An example of the usage of a headword.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Example {
+    /// the example in the source language
+    pub quote: String,
+    /// translations of the example
+    pub translations: Vec<String>,
+}
+
+/// XML element of an entry or the header, kept in memory while it is converted.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn elements(&self) -> impl Iterator<Item=&Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(e) => Some(e),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn elements_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item=&'a Element> {
+        self.elements().filter(move |e| e.name == name)
+    }
+
+    fn find<'a>(&'a self, path: &[&'a str]) -> Option<&'a Element> {
+        match path.split_first() {
+            None => Some(self),
+            Some((name, rest)) => self.elements_named(name).find_map(|e| e.find(rest)),
+        }
+    }
+
+    /// Text content with white space collapsed; referenced headwords are put in curly braces.
+    fn text(&self) -> String {
+        let mut text = String::new();
+        self.collect_text(&mut text);
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn collect_text(&self, text: &mut String) {
+        for node in &self.children {
+            match node {
+                Node::Text(t) => text.push_str(t),
+                Node::Element(e) if e.name == "ref" => {
+                    text.push('{');
+                    e.collect_text(text);
+                    text.push('}');
+                },
+                Node::Element(e) => e.collect_text(text),
+            }
+        }
+    }
+
+    /// Values of a `gramGrp` or `usg`: the text of each leaf element, or the own text.
+    fn grammar(&self) -> Vec<String> {
+        if self.elements().next().is_none() {
+            return vec![self.text()].into_iter().filter(|t| !t.is_empty()).collect();
+        }
+        self.elements().flat_map(|e| e.grammar()).collect()
+    }
+}
+
+/// Parse a TEI file into its metadata and entries.
+///
+/// The short name of the database is taken from the title and edition in the `teiHeader`, the
+/// long description additionally lists the notes from its `notesStmt`.
+pub fn parse_tei<B: BufRead>(reader: B) -> Result<(DatabaseInfo, Vec<Entry>), DictError> {
+    let mut reader = Reader::from_reader(reader);
+    let mut info = DatabaseInfo { utf8: true, ..DatabaseInfo::default() };
+    let mut entries = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        let start = match reader.read_event_into(&mut buf).map_err(xml_error)? {
+            Event::Start(start) => start.into_owned(),
+            Event::Eof => break,
+            _ => {
+                buf.clear();
+                continue;
+            },
+        };
+        buf.clear();
+        match local_name(&start).as_str() {
+            "entry" => entries.push(parse_entry(&read_element(&mut reader, &start, 1)?)),
+            "teiHeader" => info = parse_header(&read_element(&mut reader, &start, 1)?),
+            _ => (),
+        }
+    }
+    Ok((info, entries))
+}
+
+/// Convert a TEI file into a builder for a dict database.
+pub fn convert<B: BufRead>(reader: B) -> Result<DictionaryBuilder, DictError> {
+    let (info, entries) = parse_tei(reader)?;
+    let mut builder = DictionaryBuilder::new();
+    builder.set_info(info);
+    for entry in entries.iter().filter(|e| !e.headwords.is_empty()) {
+        let headwords: Vec<&str> = entry.headwords.iter().map(|h| h.as_str()).collect();
+        builder.add_entry(&headwords, &render(entry))?;
+    }
+    Ok(builder)
+}
+
+/// Convert a TEI file into a dict file and an index.
+///
+/// The dict file is compressed if its name ends on `.dz`.
+pub fn convert_file<P: AsRef<Path>>(tei_path: P, dict_path: P, index_path: P)
+        -> Result<(), DictError> {
+    let builder = convert(BufReader::new(File::open(tei_path)?))?;
+    builder.write_files(dict_path, index_path)
+}
+
+/// Render an entry into the plain-text layout of the FreeDict databases.
+pub fn render(entry: &Entry) -> String {
+    let mut text = entry.headwords.join(", ");
+    for pronunciation in &entry.pronunciations {
+        text.push_str(&format!(" /{}/", pronunciation));
+    }
+    if !entry.inflections.is_empty() {
+        let forms: Vec<String> = entry.inflections.iter()
+            .map(|f| format!("{}{}", f.orth, render_grammar(&f.grammar)))
+            .collect();
+        text.push_str(&format!(" ({})", forms.join(", ")));
+    }
+    text.push_str(&render_grammar(&entry.grammar));
+    text.push('\n');
+
+    for (number, sense) in entry.senses.iter().enumerate() {
+        for definition in &sense.definitions {
+            text.push_str(&format!("({})\n", definition));
+        }
+        let mut line = sense.grammar.iter().map(|g| format!("<{}>", g)).collect::<Vec<_>>();
+        if !sense.translations.is_empty() {
+            line.push(sense.translations.join(", "));
+        }
+        if !line.is_empty() {
+            text.push(' ');
+            if entry.senses.len() > 1 {
+                text.push_str(&format!("{}. ", number + 1));
+            }
+            text.push_str(&line.join(" "));
+        }
+        text.push('\n');
+        for example in &sense.examples {
+            text.push_str(&format!("{}\"{}\"", EXAMPLE_INDENTATION, example.quote));
+            if !example.translations.is_empty() {
+                text.push_str(&format!(" - {}", example.translations.join(", ")));
+            }
+            text.push('\n');
+        }
+        for note in &sense.notes {
+            text.push_str(&format!("{}Note: {}\n", NOTE_INDENTATION, note));
+        }
+    }
+    for note in &entry.notes {
+        text.push_str(&format!("{}Note: {}\n", NOTE_INDENTATION, note));
+    }
+    text
+}
+
+fn render_grammar(grammar: &[String]) -> String {
+    if grammar.is_empty() {
+        String::new()
+    } else {
+        format!(" <{}>", grammar.join(", "))
+    }
+}
+
+fn parse_entry(element: &Element) -> Entry {
+    let mut entry = Entry::default();
+    for child in element.elements() {
+        match child.name.as_str() {
+            "form" => parse_form(child, &mut entry),
+            "gramGrp" => entry.grammar.extend(child.grammar()),
+            "sense" => parse_sense(child, &mut entry.senses),
+            "note" | "xr" => entry.notes.push(child.text()),
+            _ => (),
+        }
+    }
+    entry
+}
+
+fn parse_form(form: &Element, entry: &mut Entry) {
+    if form.attribute("type") == Some("infl") {
+        let orth = form.elements_named("orth").map(|o| o.text()).collect::<Vec<_>>();
+        let grammar = form.elements_named("gramGrp").flat_map(|g| g.grammar()).collect();
+        entry.inflections.push(Form { orth: orth.join(", "), grammar });
+        return;
+    }
+    for child in form.elements() {
+        match child.name.as_str() {
+            "orth" => entry.headwords.push(child.text()),
+            "pron" => entry.pronunciations.push(child.text()),
+            "form" => parse_form(child, entry),
+            "gramGrp" => entry.grammar.extend(child.grammar()),
+            _ => (),
+        }
+    }
+}
+
+/// Parse a sense; nested senses are added as senses of their own.
+fn parse_sense(element: &Element, senses: &mut Vec<Sense>) {
+    let mut sense = Sense::default();
+    let mut nested = Vec::new();
+    for child in element.elements() {
+        match child.name.as_str() {
+            "gramGrp" | "usg" => sense.grammar.extend(child.grammar()),
+            "def" => sense.definitions.push(child.text()),
+            "cit" if child.attribute("type") == Some("example") => {
+                let quote = child.elements_named("quote").map(|q| q.text()).collect::<Vec<_>>();
+                sense.examples.push(Example { quote: quote.join(" "),
+                        translations: translations(child) });
+            },
+            "cit" => sense.translations.extend(child.elements_named("quote").map(|q| q.text())),
+            "note" | "xr" => sense.notes.push(child.text()),
+            "sense" => parse_sense(child, &mut nested),
+            _ => (),
+        }
+    }
+    if sense != Sense::default() || nested.is_empty() {
+        senses.push(sense);
+    }
+    senses.extend(nested);
+}
+
+/// Translations of an example, given as nested `cit` elements.
+fn translations(example: &Element) -> Vec<String> {
+    example.elements_named("cit")
+        .flat_map(|cit| cit.elements_named("quote").map(|q| q.text()))
+        .collect()
+}
+
+fn parse_header(header: &Element) -> DatabaseInfo {
+    let title = header.find(&["fileDesc", "titleStmt", "title"]).map(|t| t.text());
+    let edition = header.find(&["fileDesc", "editionStmt", "edition"]).map(|e| e.text());
+    let short_name = title.as_ref().map(|title| match edition {
+        Some(ref edition) => format!("{} ver. {}", title, edition),
+        None => title.clone(),
+    });
+    let mut info = title.clone().unwrap_or_default();
+    if let Some(ref edition) = edition {
+        info.push_str(&format!("\n\nEdition: {}", edition));
+    }
+    if let Some(notes) = header.find(&["fileDesc", "notesStmt"]) {
+        info.push_str("\n\nNotes:\n");
+        for note in notes.elements_named("note") {
+            info.push_str(&format!("\n * {}\n", note.text()));
+        }
+    }
+    let url = header.find(&["fileDesc", "publicationStmt", "pubPlace", "ref"])
+        .and_then(|r| r.attribute("target").map(String::from).or_else(|| Some(r.text())));
+    DatabaseInfo { short_name, info: title.map(|_| info), url, utf8: true,
+            ..DatabaseInfo::default() }
+}
+
+/// Create an element without children from a start tag.
+fn new_element(start: &BytesStart) -> Result<Element, DictError> {
+    let mut element = Element { name: local_name(start), ..Element::default() };
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(|e| xml_error(e.into()))?;
+        let name = String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned();
+        let value = attribute.unescape_value().map_err(xml_error)?.into_owned();
+        element.attributes.push((name, value));
+    }
+    Ok(element)
+}
+
+/// Read an element with all its descendants; `start` must have been read already.
+///
+/// `depth` is the nesting depth of the element, counting from the entry or header; deeper
+/// nesting than `MAX_DEPTH` is refused.
+fn read_element<B: BufRead>(reader: &mut Reader<B>, start: &BytesStart, depth: usize)
+        -> Result<Element, DictError> {
+    let mut element = new_element(start)?;
+    if depth > MAX_DEPTH {
+        return Err(DictError::InvalidFileFormat(format!("elements of the TEI file are nested \
+                deeper than {} levels within <{}>", MAX_DEPTH, element.name), None));
+    }
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf).map_err(xml_error)? {
+            Event::Start(child) => {
+                let child = child.into_owned();
+                element.children.push(Node::Element(read_element(reader, &child, depth + 1)?));
+            },
+            Event::Empty(child) => element.children.push(Node::Element(new_element(&child)?)),
+            Event::Text(text) => element.children.push(Node::Text(
+                    text.unescape().map_err(xml_error)?.into_owned())),
+            Event::CData(data) => element.children.push(Node::Text(
+                    data.decode().map_err(|e| xml_error(e.into()))?.into_owned())),
+            Event::End(_) => return Ok(element),
+            Event::Eof => return Err(DictError::InvalidFileFormat(format!("unexpected end of \
+                    TEI file within <{}>", element.name), None)),
+            _ => (),
+        }
+        buf.clear();
+    }
+}
+
+fn local_name(start: &BytesStart) -> String {
+    String::from_utf8_lossy(start.local_name().as_ref()).into_owned()
+}
+
+fn xml_error(err: quick_xml::Error) -> DictError {
+    DictError::InvalidFileFormat(format!("invalid TEI file: {}", err), None)
+}
@@ -0,0 +1,168 @@
+//! Consistency checks of an index against its dict file.
+//!
+//! Errors in an index file usually surface only when the affected headword is looked up. The
+//! checks in this module walk the whole index in the order of the dict file instead and report
+//! all [Issue](enum.Issue.html)s found. Not every issue is necessarily an error: `dictfmt` e.g.
+//! writes the same span for each alternative headword of an entry.
+
+use std::fmt;
+use std::io::ErrorKind;
+
+use crate::collation::Collation;
+use crate::dictreader::DictReader;
+use crate::errors::DictError;
+use crate::indexing::{self, Index, Location};
+
+/// meta entries which each database should contain
+const REQUIRED_META_ENTRIES: [&str; 2] = ["00-database-short", "00-database-info"];
+
+/// A problem found while validating a dictionary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// the definition of the headword reaches beyond the end of the dict data
+    OutOfRange(String, Location),
+    /// the definitions of two headwords overlap partially
+    Overlap(String, String, Location),
+    /// several headwords point to exactly the same definition
+    DuplicateSpan(Vec<String>, Location),
+    /// bytes of the dict data, given as offset and length, not referenced by any headword
+    Gap(u64, u64),
+    /// the definition does not start with the headword
+    HeadwordMismatch(String, Location),
+    /// the definition is not valid UTF-8
+    InvalidUtf8(String, Location),
+    /// the definition could not be read, e.g. because it is too long or the compressed data is
+    /// corrupt; contains the error message
+    Unreadable(String, Location, String),
+    /// a meta entry expected in every database is missing
+    MissingMetaEntry(String),
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Issue::OutOfRange(ref word, ref loc) => write!(f, "{}: definition at {} with \
+                    length {} reaches beyond the end of the data", word, loc.offset, loc.length),
+            Issue::Overlap(ref word, ref other, ref loc) => write!(f, "{}: definition at {} \
+                    with length {} overlaps with the one of {}", word, loc.offset, loc.length,
+                    other),
+            Issue::DuplicateSpan(ref words, ref loc) => write!(f, "{} share the definition at \
+                    {} with length {}", words.join(", "), loc.offset, loc.length),
+            Issue::Gap(offset, length) => write!(f, "{} bytes at offset {} are not referenced",
+                    length, offset),
+            Issue::HeadwordMismatch(ref word, ref loc) => write!(f, "{}: definition at {} \
+                    does not start with the headword", word, loc.offset),
+            Issue::InvalidUtf8(ref word, ref loc) => write!(f, "{}: definition at {} is not \
+                    valid UTF-8", word, loc.offset),
+            Issue::Unreadable(ref word, ref loc, ref error) => write!(f, "{}: definition at {} \
+                    could not be read: {}", word, loc.offset, error),
+            Issue::MissingMetaEntry(ref name) => write!(f, "meta entry {} is missing", name),
+        }
+    }
+}
+
+/// Check whether a (normalised) headword is a meta entry like `00-database-short`.
+pub fn is_meta_headword(headword: &str) -> bool {
+    headword.starts_with("00-database-") || headword.starts_with("00database")
+}
+
+/// Validate an index against the dict data read by `reader`.
+///
+/// Headwords are reported in their original spelling. The `collation` is used to compare
+/// headwords with the beginning of their definitions and to find meta entries. Definitions which
+/// cannot be read are reported as issues of their headwords, the validation goes on with the
+/// next one.
+///
+/// If the reader does not know the length of the data, unreferenced bytes at the end cannot be
+/// detected.
+pub fn validate(reader: &mut dyn DictReader, index: &Index, collation: &Collation)
+        -> Result<Vec<Issue>, DictError> {
+    validate_entries(reader, &indexing::entries(index), collation)
+}
+
+/// Check the locations of all (normalised) headwords against the dict data.
+pub(crate) fn validate_entries(reader: &mut dyn DictReader, entries: &[(&str, &Location)],
+        collation: &Collation) -> Result<Vec<Issue>, DictError> {
+    let mut issues = Vec::new();
+    for name in REQUIRED_META_ENTRIES.iter() {
+        let key = collation.normalize(name);
+        if !entries.iter().any(|(k, _)| *k == key) {
+            issues.push(Issue::MissingMetaEntry(name.to_string()));
+        }
+    }
+
+    let mut entries: Vec<(&str, &Location)> = entries.iter()
+        .map(|&(key, l)| (l.original.as_deref().unwrap_or(key), l))
+        .collect();
+    entries.sort_by(|a, b| (a.1.offset, a.1.length, a.0).cmp(&(b.1.offset, b.1.length, b.0)));
+
+    let total_length = reader.total_length();
+    // end of the bytes covered so far and the headword which covers them
+    let mut covered_until = 0;
+    let mut covering_headword = "";
+    let mut position = 0;
+    while position < entries.len() {
+        let (headword, location) = entries[position];
+        // all headwords sharing this span
+        let same_span = entries[position..].iter()
+            .take_while(|(_, l)| l.offset == location.offset && l.length == location.length)
+            .count();
+        let headwords: Vec<&str> = entries[position..position + same_span].iter()
+            .map(|(h, _)| *h)
+            .collect();
+        position += same_span;
+
+        let end = match location.offset.checked_add(location.length) {
+            Some(end) if total_length.is_none_or(|total_length| end <= total_length) => end,
+            _ => {
+                issues.extend(headwords.iter().map(|h|
+                        Issue::OutOfRange(h.to_string(), location.clone())));
+                continue;
+            },
+        };
+        if same_span > 1 {
+            issues.push(Issue::DuplicateSpan(headwords.iter().map(|h| h.to_string()).collect(),
+                    location.clone()));
+        }
+        if location.offset > covered_until {
+            issues.push(Issue::Gap(covered_until, location.offset - covered_until));
+        } else if location.offset < covered_until && location.length > 0 {
+            issues.push(Issue::Overlap(headword.to_string(), covering_headword.to_string(),
+                    location.clone()));
+        }
+        if end > covered_until {
+            covered_until = end;
+            covering_headword = headword;
+        }
+
+        match reader.fetch_definition(location.offset, location.length) {
+            Ok(definition) => {
+                // compare word by word, punctuation may have been dropped from the headword
+                let words = |s: &str| collation.normalize(s).split_whitespace()
+                    .map(String::from).collect::<Vec<_>>();
+                let definition_words = words(definition.lines().next().unwrap_or(""));
+                for headword in headwords.iter().filter(|h| !is_meta_headword(h)) {
+                    if !definition_words.starts_with(&words(headword)) {
+                        issues.push(Issue::HeadwordMismatch(headword.to_string(),
+                                location.clone()));
+                    }
+                }
+            },
+            Err(DictError::Utf8Error(_)) => issues.extend(headwords.iter().map(|h|
+                    Issue::InvalidUtf8(h.to_string(), location.clone()))),
+            // without the length of the data, this is the only hint at an entry out of range
+            Err(DictError::IoError(ref e)) if total_length.is_none()
+                    && e.kind() == ErrorKind::UnexpectedEof =>
+                issues.extend(headwords.iter().map(|h|
+                        Issue::OutOfRange(h.to_string(), location.clone()))),
+            Err(e) => issues.extend(headwords.iter().map(|h|
+                    Issue::Unreadable(h.to_string(), location.clone(), e.to_string()))),
+        }
+    }
+    if let Some(total_length) = total_length {
+        if covered_until < total_length {
+            issues.push(Issue::Gap(covered_until, total_length - covered_until));
+        }
+    }
+    Ok(issues)
+}
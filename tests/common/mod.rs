@@ -0,0 +1,55 @@
+//! Helpers shared by the integration tests.
+//!
+//! Each test file is compiled on its own and uses only some of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use dict::builder::DictionaryBuilder;
+use dict::dictreader::DictReaderRaw;
+use dict::indexing::parse_index;
+use dict::metadata::DatabaseInfo;
+use dict::{load_dictionary, load_dictionary_from_file, Dictionary};
+
+/// get the path of a file in tests/assets
+pub fn get_asset_path(fname: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("assets").join(fname)
+}
+
+/// load the Latin - German FreeDict dictionary from tests/assets
+pub fn example_dictionary() -> Dictionary {
+    load_dictionary_from_file(get_asset_path("lat-deu.dict.dz"), get_asset_path("lat-deu.index"))
+        .unwrap()
+}
+
+/// build a dictionary from a text and index lines
+pub fn mk_dict<T: AsRef<[u8]>>(text: T, index: &str) -> Dictionary {
+    let reader = DictReaderRaw::new(Cursor::new(text.as_ref().to_vec())).unwrap();
+    load_dictionary(Box::new(reader), parse_index(Cursor::new(index)).unwrap())
+}
+
+/// create a builder for a UTF-8 database with one headword per entry
+pub fn mk_builder(short_name: Option<&str>, entries: &[(&str, &str)]) -> DictionaryBuilder {
+    let mut builder = DictionaryBuilder::new();
+    builder.set_info(DatabaseInfo { short_name: short_name.map(String::from), utf8: true,
+            ..DatabaseInfo::default() });
+    for (headword, definition) in entries {
+        builder.add_entry(&[headword], definition).unwrap();
+    }
+    builder
+}
+
+/// create a fresh, empty directory `libdict-{name}-{pid}` in the temporary directory
+pub fn mk_workdir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("libdict-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// remove a directory created with `mk_workdir`
+pub fn cleanup(dir: &Path) {
+    fs::remove_dir_all(dir).unwrap();
+}
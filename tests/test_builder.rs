@@ -0,0 +1,151 @@
+use std::fs;
+use std::io::Read;
+
+use dict::*;
+use dict::builder::DictionaryBuilder;
+use dict::metadata::DatabaseInfo;
+
+mod common;
+use common::{get_asset_path, mk_workdir};
+
+fn example_builder() -> DictionaryBuilder {
+    let mut builder = DictionaryBuilder::new();
+    builder.set_info(DatabaseInfo { short_name: Some("Test dictionary".into()),
+            info: Some("Made by a test.".into()), utf8: true, ..DatabaseInfo::default() });
+    builder.add_entry(&["mater", "Mutter"], "mater (matris) <n, sg, f>\n Mutter").unwrap();
+    builder.add_entry(&["Roma"], "Roma <n, sg, f>\n Rom\n").unwrap();
+    builder
+}
+
+#[test]
+fn test_built_dictionary_can_be_looked_up() {
+    let mut dict = example_builder().build().unwrap();
+    assert_eq!(dict.lookup("mater").unwrap(), "mater (matris) <n, sg, f>\n Mutter\n");
+    assert_eq!(dict.lookup("mutter").unwrap(), dict.lookup("mater").unwrap());
+    assert_eq!(dict.lookup_with_headword("roma").unwrap().0, "Roma");
+    assert_eq!(dict.short_name().unwrap(), "Test dictionary");
+    let info = dict.info().unwrap();
+    assert_eq!(info.info.as_deref(), Some("Made by a test."));
+    assert!(info.utf8);
+}
+
+#[test]
+fn test_index_is_sorted_and_keeps_original_headwords() {
+    let (mut data, mut index) = (Vec::new(), Vec::new());
+    example_builder().write(&mut data, &mut index, false).unwrap();
+    let index = String::from_utf8(index).unwrap();
+    let keys: Vec<&str> = index.lines().map(|l| l.split('\t').next().unwrap()).collect();
+    assert_eq!(keys, vec!["00databaseinfo", "00databaseshort", "00databaseutf8", "mater",
+            "mutter", "roma"]);
+    assert!(index.lines().any(|l| l.ends_with("\tRoma")));
+    assert!(index.lines().any(|l| l.starts_with("mater\t") && l.split('\t').count() == 3));
+}
+
+#[test]
+fn test_allchars_keeps_punctuation_in_keys() {
+    let mut builder = DictionaryBuilder::new();
+    builder.set_info(DatabaseInfo { allchars: true, ..DatabaseInfo::default() });
+    builder.add_entry(&["a-b"], "a-b").unwrap();
+    let mut dict = builder.build().unwrap();
+    assert!(dict.contains_unchecked("a-b"));
+    assert!(dict.contains_unchecked("00-database-allchars"));
+    assert!(dict.lookup("ab").is_err());
+}
+
+#[test]
+fn test_validation_of_built_dictionary_finds_no_issues() {
+    let mut builder = example_builder();
+    builder.add_entry(&["ferrugo"], "ferrugo (ferruginis) <n, sg, f>\n Rost").unwrap();
+    let issues = builder.build().unwrap().validate().unwrap();
+    // only the alternative headword of "mater" is reported
+    assert_eq!(issues.len(), 2);
+    assert!(issues.iter().all(|i| i.to_string().contains("Mutter")));
+}
+
+#[test]
+fn test_headwords_with_tabs_are_rejected() {
+    let mut builder = DictionaryBuilder::new();
+    assert!(builder.add_entry(&["a\tb"], "a").is_err());
+    assert!(builder.is_empty());
+}
+
+#[test]
+fn test_written_files_can_be_loaded() {
+    let dir = mk_workdir("builder-files");
+    for name in ["test.dict", "test.dict.dz"].iter() {
+        example_builder().write_files(dir.join(name), dir.join("test.index")).unwrap();
+        let mut dict = load_dictionary_from_file(dir.join(name), dir.join("test.index"))
+            .unwrap();
+        assert_eq!(dict.lookup("roma").unwrap(), "Roma <n, sg, f>\n Rom\n");
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_large_dictzip_files_have_several_chunks_and_are_valid_gzip() {
+    let dir = mk_workdir("builder-dictzip");
+    let mut builder = DictionaryBuilder::new();
+    // definitions crossing and ending at chunk borders
+    let definition = "x".repeat(999);
+    for number in 0..200 {
+        builder.add_entry(&[&format!("word{}", number)], &definition).unwrap();
+    }
+    let dz = dir.join("large.dict.dz");
+    builder.write_files(&dz, &dir.join("large.index")).unwrap();
+    builder.write_files(&dir.join("large.dict"), &dir.join("large.index")).unwrap();
+
+    let mut uncompressed = Vec::new();
+    flate2::read::GzDecoder::new(fs::File::open(&dz).unwrap())
+        .read_to_end(&mut uncompressed).unwrap();
+    assert_eq!(uncompressed, fs::read(dir.join("large.dict")).unwrap());
+
+    let mut dict = load_dictionary_from_file(dz, dir.join("large.index")).unwrap();
+    let mut entries = 0;
+    for entry in dict.entries() {
+        assert_eq!(entry.unwrap().1.len(), 1000);
+        entries += 1;
+    }
+    assert_eq!(entries, 200);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_rebuilt_freedict_dictionary_gives_same_definitions() {
+    let dir = mk_workdir("builder-rebuild");
+    let mut original = load_dictionary_from_file(get_asset_path("lat-deu.dict.dz"),
+            get_asset_path("lat-deu.index")).unwrap();
+    let mut builder = DictionaryBuilder::new();
+    builder.set_info(original.info().unwrap());
+    for entry in original.entries() {
+        let (headword, definition) = entry.unwrap();
+        builder.add_entry(&[&headword], &definition).unwrap();
+    }
+    builder.write_files(dir.join("lat-deu.dict.dz"), dir.join("lat-deu.index")).unwrap();
+    let mut rebuilt = load_dictionary_from_file(dir.join("lat-deu.dict.dz"),
+            dir.join("lat-deu.index")).unwrap();
+    for word in ["ferrugo", "a puero", "mater"].iter() {
+        assert_eq!(rebuilt.lookup(word).unwrap(), original.lookup(word).unwrap());
+    }
+    assert_eq!(rebuilt.lookup_all("abitum").unwrap().len(), 2);
+    assert_eq!(rebuilt.info().unwrap(), original.info().unwrap());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_meta_entries_start_with_their_headword() {
+    let mut dict = example_builder().build().unwrap();
+    assert_eq!(dict.lookup("00-database-short").unwrap(), "00-database-short\nTest dictionary\n");
+    assert_eq!(dict.lookup("00-database-utf8").unwrap(), "00-database-utf8\n");
+}
+
+#[test]
+fn test_headwords_without_letters_are_rejected() {
+    let mut builder = example_builder();
+    builder.add_entry(&["..."], "dots").unwrap();
+    assert!(builder.build().is_err());
+    // with allchars, punctuation is kept in the index
+    let mut info = builder.info().clone();
+    info.allchars = true;
+    builder.set_info(info);
+    assert!(builder.build().unwrap().contains_unchecked("..."));
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use dict::*;
+use dict::cache::*;
+use dict::indexing::parse_index_from_file;
+
+mod common;
+use common::{get_asset_path, mk_workdir};
+
+// create a fresh directory with a copy of the example index
+fn mk_index_dir(name: &str) -> PathBuf {
+    let dir = mk_workdir(name);
+    fs::copy(get_asset_path("lat-deu.index"), dir.join("lat-deu.index")).unwrap();
+    dir
+}
+
+#[test]
+fn test_cache_is_written_and_reused() {
+    let dir = mk_index_dir("cache-reuse");
+    let index_path = dir.join("lat-deu.index");
+    let location = CacheLocation::NextToIndex;
+    assert!(!is_fresh(&index_path, &location));
+
+    let parsed = load_index_cached(&index_path, &location).unwrap();
+    assert!(dir.join("lat-deu.index.cache").exists());
+    assert!(is_fresh(&index_path, &location));
+    assert_eq!(load_index_cached(&index_path, &location).unwrap(), parsed);
+    assert_eq!(parsed, parse_index_from_file(&index_path).unwrap());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_changed_index_invalidates_cache() {
+    let dir = mk_index_dir("cache-stale");
+    let index_path = dir.join("lat-deu.index");
+    let location = CacheLocation::NextToIndex;
+    load_index_cached(&index_path, &location).unwrap();
+
+    let mut file = fs::OpenOptions::new().append(true).open(&index_path).unwrap();
+    file.write_all(b"zzznewword\tA\tB\n").unwrap();
+    drop(file);
+    assert!(!is_fresh(&index_path, &location));
+    let index = load_index_cached(&index_path, &location).unwrap();
+    assert!(index.contains_key("zzznewword"));
+    assert!(is_fresh(&index_path, &location));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cache_directory_is_used() {
+    let dir = mk_index_dir("cache-dir");
+    let index_path = dir.join("lat-deu.index");
+    let location = CacheLocation::Directory(dir.join("cache"));
+    load_index_cached(&index_path, &location).unwrap();
+    let cache_path = location.cache_path(&index_path);
+    assert!(cache_path.starts_with(dir.join("cache")));
+    assert!(cache_path.exists());
+    assert!(!dir.join("lat-deu.index.cache").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_caches_are_only_used_on_request() {
+    let dir = mk_index_dir("cache-dictionary");
+    let index_path = dir.join("lat-deu.index");
+    let cache_path = dir.join("lat-deu.index.cache");
+    let dict_path = get_asset_path("lat-deu.dict.dz");
+    let mut dict = load_dictionary_from_file(&dict_path, &index_path).unwrap();
+    assert!(dict.lookup("mater").unwrap().starts_with("mater"));
+    assert!(!cache_path.exists());
+    let mut dict = load_dictionary_from_file_cached(&dict_path, &index_path,
+            &CacheLocation::NextToIndex).unwrap();
+    assert!(dict.lookup("mater").unwrap().starts_with("mater"));
+
+    // corrupt the cache, but keep the fingerprint of the index: must be detected
+    let mut data = fs::read(&cache_path).unwrap();
+    data.truncate(data.len() / 2);
+    fs::write(&cache_path, data).unwrap();
+    let mut dict = load_dictionary_from_file(&dict_path, &index_path).unwrap();
+    assert!(dict.lookup("vultus").unwrap().starts_with("vultus"));
+    assert!(!is_fresh(&index_path, &CacheLocation::NextToIndex));
+    let mut dict = load_dictionary_from_file_cached(&dict_path, &index_path,
+            &CacheLocation::NextToIndex).unwrap();
+    assert!(dict.lookup("vultus").unwrap().starts_with("vultus"));
+    assert!(is_fresh(&index_path, &CacheLocation::NextToIndex));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,56 @@
+use dict::collation::Collation;
+use dict::matching::MatchOptions;
+
+mod common;
+use common::{example_dictionary, mk_dict};
+
+#[test]
+fn test_queries_are_normalised_like_dictd() {
+    let mut dict = example_dictionary();
+    assert_eq!(dict.collation(), Collation::default());
+    assert!(dict.lookup("A Puero").unwrap().starts_with("a puero"));
+    assert!(dict.lookup("a, puero!").unwrap().starts_with("a puero"));
+    assert!(dict.contains("MATER"));
+}
+
+#[test]
+fn test_match_queries_are_normalised() {
+    let dict = example_dictionary();
+    let matches = dict.match_words("prefix", "A Pue", &MatchOptions::default()).unwrap();
+    assert_eq!(matches, vec!["a puero"]);
+}
+
+#[test]
+fn test_headwords_of_index_are_normalised() {
+    let mut dict = mk_dict("firstsecond", "A-B\tA\tF\nab\tF\tG");
+    assert_eq!(dict.lookup_all("ab").unwrap(), vec!["first", "second"]);
+}
+
+#[test]
+fn test_allchars_header_keeps_punctuation() {
+    let mut dict = mk_dict("firstsecond\n", "00-database-allchars\tL\tB\nA-B\tA\tF\nab\tF\tG");
+    assert_eq!(dict.collation(), Collation { allchars: true, case_sensitive: false });
+    assert_eq!(dict.lookup("a-b").unwrap(), "first");
+    assert_eq!(dict.lookup("AB").unwrap(), "second");
+}
+
+#[test]
+fn test_case_sensitive_header_keeps_case() {
+    let mut dict = mk_dict("firstsecond\n",
+                           "00databasecasesensitive\tL\tB\nRosa\tA\tF\nrosa\tF\tG");
+    assert_eq!(dict.collation(), Collation { allchars: false, case_sensitive: true });
+    assert_eq!(dict.lookup("Rosa").unwrap(), "first");
+    assert_eq!(dict.lookup("rosa").unwrap(), "second");
+    assert!(dict.lookup("ROSA").is_err());
+}
+
+#[test]
+fn test_original_headwords_from_fourth_index_column_are_returned() {
+    let mut dict = mk_dict("Rosa, die Rosemater", "rosa\tA\tO\tRosa\nmater\tO\tF");
+    assert_eq!(dict.lookup_with_headword("ROSA").unwrap(),
+               ("Rosa".to_string(), "Rosa, die Rose".to_string()));
+    assert_eq!(dict.lookup_all_with_headwords("mater").unwrap(),
+               vec![("mater".to_string(), "mater".to_string())]);
+    let matches = dict.match_words("prefix", "r", &MatchOptions::default()).unwrap();
+    assert_eq!(matches, vec!["Rosa"]);
+}
@@ -0,0 +1,234 @@
+use dict::collection::*;
+use dict::errors::DictError;
+use dict::matching::{HeadwordIndex, MatchOptions};
+use dict::strategy::MatchStrategy;
+use dict::*;
+
+mod common;
+use common::{get_asset_path, mk_builder, mk_dict};
+
+fn mk_collection() -> DictionaryCollection {
+    let mut collection = DictionaryCollection::new();
+    collection.insert("fd-lat-deu", load_dictionary_from_file(get_asset_path("lat-deu.dict.dz"),
+            get_asset_path("lat-deu.index")).unwrap());
+    collection.insert("lat-eng", mk_builder(Some("Latin - English"),
+            &[("rosa", "rosa\n rose"), ("ferrugo", "ferrugo\n rust")]).build().unwrap());
+    collection.insert("misc", mk_builder(None, &[("rosa", "Rosa\n a name")]).build().unwrap());
+    collection
+}
+
+#[test]
+fn test_databases_keep_their_order() {
+    let mut collection = mk_collection();
+    assert_eq!(collection.names(), vec!["fd-lat-deu", "lat-eng", "misc"]);
+    let replaced = collection.insert("fd-lat-deu", mk_builder(None, &[]).build().unwrap());
+    assert!(replaced.is_some());
+    assert_eq!(collection.names(), vec!["fd-lat-deu", "lat-eng", "misc"]);
+    assert!(collection.get_mut("fd-lat-deu").unwrap().lookup("ferrugo").is_err());
+    assert!(collection.remove("lat-eng").is_some());
+    assert!(collection.remove("lat-eng").is_none());
+    assert_eq!(collection.len(), 2);
+    assert!(!collection.contains("lat-eng"));
+}
+
+#[test]
+fn test_lookup_in_all_databases() {
+    let mut collection = mk_collection();
+    let definitions = collection.lookup(ALL_DATABASES, "ferrugo").unwrap();
+    assert_eq!(definitions.len(), 2);
+    assert_eq!(definitions[0].database, "fd-lat-deu");
+    assert_eq!(definitions[0].description, "Latin - German FreeDict dictionary ver. 0.4");
+    assert!(definitions[0].definition.contains("Rost"));
+    assert_eq!(definitions[1], Definition { database: "lat-eng".into(),
+            description: "Latin - English".into(), headword: "ferrugo".into(),
+            definition: "ferrugo\n rust\n".into() });
+    // databases without a short description are described by their name
+    let definitions = collection.lookup("*", "rosa").unwrap();
+    assert_eq!(definitions.last().unwrap().description, "misc");
+}
+
+#[test]
+fn test_lookup_in_first_database_with_a_result() {
+    let mut collection = mk_collection();
+    let definitions = collection.lookup(FIRST_MATCH, "ferrugo").unwrap();
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].database, "fd-lat-deu");
+    let definitions = collection.lookup("!", "rosa").unwrap();
+    assert!(definitions.iter().all(|d| d.database == "fd-lat-deu"));
+}
+
+#[test]
+fn test_lookup_in_a_single_database() {
+    let mut collection = mk_collection();
+    let definitions = collection.lookup("lat-eng", "rosa").unwrap();
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].definition, "rosa\n rose\n");
+    match collection.lookup("misc", "ferrugo") {
+        Err(DictError::WordNotFound(_)) => (),
+        other => panic!("expected WordNotFound, got {:?}", other),
+    }
+    match collection.lookup("eng-lat", "rosa") {
+        Err(DictError::UnknownDatabase(name)) => assert_eq!(name, "eng-lat"),
+        other => panic!("expected UnknownDatabase, got {:?}", other),
+    }
+    assert!(collection.lookup("*", "testtesttest").is_err());
+}
+
+#[test]
+fn test_matches_are_tagged_with_their_database() {
+    let mut collection = mk_collection();
+    let options = MatchOptions::default();
+    let matches = collection.match_words("*", "prefix", "ros", &options).unwrap();
+    let databases: Vec<&str> = matches.iter().map(|m| m.database.as_str()).collect();
+    assert!(databases.contains(&"fd-lat-deu") && databases.contains(&"misc"));
+    assert!(matches.contains(&Match { database: "lat-eng".into(), headword: "rosa".into() }));
+    let first = collection.match_words("!", "exact", "rosa", &options).unwrap();
+    assert!(first.iter().all(|m| m.database == "fd-lat-deu"));
+    match collection.match_words("misc", "prefix", "xyz", &options) {
+        Err(DictError::WordNotFound(_)) => (),
+        other => panic!("expected WordNotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_descriptions_of_all_databases() {
+    let mut collection = mk_collection();
+    let descriptions = collection.descriptions().unwrap();
+    assert_eq!(descriptions[1], ("lat-eng".to_string(), "Latin - English".to_string()));
+    assert_eq!(descriptions[2], ("misc".to_string(), "misc".to_string()));
+}
+
+#[test]
+fn test_lazy_databases_are_opened_on_first_use() {
+    let mut collection = mk_collection();
+    collection.insert_lazy("lazy",
+            || mk_builder(Some("Lazy"), &[("vinum", "vinum\n Wein")]).build());
+    let mut attempts = 0;
+    collection.insert_lazy("broken", move || {
+        attempts += 1;
+        Err(DictError::InvalidFileFormat(format!("attempt {}", attempts), None))
+    });
+    assert!(!collection.is_open("lazy") && collection.get("lazy").is_none());
+    assert_eq!(collection.lookup("lazy", "vinum").unwrap()[0].description, "Lazy");
+    assert!(collection.is_open("lazy"));
+    assert!(collection.get("lazy").unwrap().contains("vinum"));
+
+    // a failing database is not tried again
+    assert!(collection.lookup("broken", "vinum").unwrap_err().to_string().contains("attempt 1"));
+    match collection.open("broken") {
+        Err(DictError::UnavailableDatabase(name, reason)) =>
+            assert_eq!((name.as_str(), reason.as_str()), ("broken", "attempt 1")),
+        Err(e) => panic!("expected UnavailableDatabase, got {:?}", e),
+        Ok(_) => panic!("broken database was opened"),
+    }
+    assert_eq!(collection.failures(), vec![("broken", "attempt 1")]);
+    assert_eq!(collection.descriptions().unwrap()[4], ("broken".to_string(),
+            "broken".to_string()));
+}
+
+#[test]
+fn test_databases_which_cannot_be_opened_are_skipped_by_all_and_first() {
+    let mut collection = mk_collection();
+    collection.insert_lazy("broken", || Err(DictError::InvalidFileFormat("broken".into(), None)));
+    collection.insert_lazy("lazy",
+            || mk_builder(Some("Lazy"), &[("temetum", "temetum\n Wein")]).build());
+    let definitions = collection.lookup(ALL_DATABASES, "temetum").unwrap();
+    assert_eq!(definitions[0].database, "lazy");
+    assert_eq!(collection.lookup(FIRST_MATCH, "temetum").unwrap()[0].database, "lazy");
+    let matches = collection.match_words(ALL_DATABASES, "exact", "temetum",
+            &MatchOptions::default()).unwrap();
+    assert_eq!(matches.last().unwrap().database, "lazy");
+    assert_eq!(collection.failures().len(), 1);
+    // a database named explicitly reports why it is unavailable
+    assert!(collection.lookup("broken", "temetum").is_err());
+}
+
+#[test]
+fn test_virtual_databases_search_their_members() {
+    let mut collection = mk_collection();
+    collection.insert_virtual("latin", vec!["lat-eng".into(), "fd-lat-deu".into()],
+            Some("All Latin dictionaries".into()));
+    assert_eq!(collection.members("latin").unwrap(), ["lat-eng", "fd-lat-deu"]);
+    assert!(collection.members("misc").is_none());
+
+    // results name the database they were found in, in the order of the members
+    let definitions = collection.lookup("latin", "ferrugo").unwrap();
+    let databases: Vec<&str> = definitions.iter().map(|d| d.database.as_str()).collect();
+    assert_eq!(databases, vec!["lat-eng", "fd-lat-deu"]);
+    assert_eq!(definitions[0].description, "Latin - English");
+    let matches = collection.match_words("latin", "exact", "rosa", &MatchOptions::default())
+            .unwrap();
+    assert!(matches.iter().all(|m| m.database == "lat-eng" || m.database == "fd-lat-deu"));
+    assert!(matches.iter().any(|m| m.database == "lat-eng"));
+
+    // virtual databases are not searched again by `*` and have no dictionary of their own
+    assert_eq!(collection.lookup("*", "ferrugo").unwrap().len(), 2);
+    assert!(collection.get("latin").is_none());
+    assert!(collection.open("latin").is_err());
+    let descriptions = collection.descriptions().unwrap();
+    assert_eq!(descriptions[3], ("latin".to_string(), "All Latin dictionaries".to_string()));
+}
+
+#[test]
+fn test_virtual_databases_with_unknown_members_are_an_error() {
+    let mut collection = mk_collection();
+    collection.insert_virtual("broken", vec!["misc".into(), "eng-lat".into()], None);
+    collection.insert_virtual("nested", vec!["broken".into()], None);
+    match collection.lookup("broken", "rosa") {
+        Err(DictError::UnknownDatabase(name)) => assert_eq!(name, "eng-lat"),
+        other => panic!("expected UnknownDatabase, got {:?}", other),
+    }
+    assert!(collection.lookup("nested", "rosa").is_err());
+    assert_eq!(collection.descriptions().unwrap()[3], ("broken".to_string(),
+            "broken".to_string()));
+}
+
+/// `exact` strategy which always fails
+struct FailingExact;
+
+impl MatchStrategy for FailingExact {
+    fn name(&self) -> &str {
+        "exact"
+    }
+
+    fn description(&self) -> &str {
+        "Always fails"
+    }
+
+    fn find(&self, _query: &str, _index: &HeadwordIndex, _options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        Err(DictError::Timeout)
+    }
+}
+
+#[test]
+fn test_databases_failing_to_search_are_skipped_by_all_and_first() {
+    let mut collection = DictionaryCollection::new();
+    let mut broken = mk_dict(b"rosa\xff\n", "rosa\tA\tF");
+    broken.register_strategy(Box::new(FailingExact));
+    collection.insert("broken", broken);
+    collection.insert("misc", mk_builder(None, &[("rosa", "Rosa\n a name")]).build().unwrap());
+    let options = MatchOptions::default();
+    for database in &[ALL_DATABASES, FIRST_MATCH] {
+        let definitions = collection.lookup(database, "rosa").unwrap();
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].database, "misc");
+        let matches = collection.match_words(database, "exact", "rosa", &options).unwrap();
+        assert_eq!(matches, vec![Match { database: "misc".into(), headword: "rosa".into() }]);
+    }
+    // a database named explicitly reports the error
+    assert!(matches!(collection.lookup("broken", "rosa"), Err(DictError::Utf8Error(_))));
+    assert!(matches!(collection.match_words("broken", "exact", "rosa", &options),
+            Err(DictError::Timeout)));
+}
+
+#[test]
+fn test_no_result_is_reported_alike_for_lookups_and_matches() {
+    let mut collection = mk_collection();
+    let options = MatchOptions::default();
+    for database in &[ALL_DATABASES, FIRST_MATCH, "misc"] {
+        assert!(matches!(collection.lookup(database, "xyz"), Err(DictError::WordNotFound(_))));
+        assert!(matches!(collection.match_words(database, "exact", "xyz", &options),
+                Err(DictError::WordNotFound(_))), "database {}", database);
+    }
+}
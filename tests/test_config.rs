@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::PathBuf;
+
+use dict::config::*;
+use dict::errors::DictError;
+
+mod common;
+use common::{cleanup, get_asset_path, mk_builder, mk_workdir};
+
+static CONFIG: &str = r#"# dictd configuration
+global {
+    port 2628
+    listen_to 127.0.0.1
+}
+access { allow 127.0.0.1; deny *.example.com
+    authonly }
+site "site info.txt"
+user jane "very secret"
+
+database fd-lat-deu {
+    data "LAT_DEU.dict.dz"
+    index "LAT_DEU.index"; default_strategy prefix
+}
+database small
+{
+    data small.dict; index small.index
+    name "Small dictionary"
+    info "A small dictionary\nfor testing"
+    access { user jane }
+    invisible
+}
+database_exit
+database hidden { data small.dict; index small.index }
+database_virtual latin {
+    name "All Latin dictionaries"
+    database_list "fd-lat-deu, small"
+}
+database_plugin judy { plugin /usr/lib/dictd/judy.so }
+"#;
+
+fn with_assets(config: &str) -> String {
+    config.replace("LAT_DEU", get_asset_path("lat-deu").to_str().unwrap())
+}
+
+// write the configuration with a small database next to it
+fn mk_config_dir(name: &str, config: &str) -> PathBuf {
+    let dir = mk_workdir(name);
+    mk_builder(None, &[("vinum", "vinum\n Wein")])
+        .write_files(dir.join("small.dict"), dir.join("small.index")).unwrap();
+    fs::write(dir.join("dictd.conf"), with_assets(config)).unwrap();
+    dir
+}
+
+#[test]
+fn test_configuration_is_parsed() {
+    let config = parse_config(CONFIG.as_bytes()).unwrap();
+    assert_eq!(config.global, vec![("port".to_string(), vec!["2628".to_string()]),
+            ("listen_to".to_string(), vec!["127.0.0.1".to_string()])]);
+    assert_eq!(config.access, vec![AccessRule::Allow("127.0.0.1".into()),
+            AccessRule::Deny("*.example.com".into()), AccessRule::AuthOnly]);
+    assert_eq!(config.site.as_deref(), Some("site info.txt"));
+    assert_eq!(config.users, vec![("jane".to_string(), "very secret".to_string())]);
+    let names: Vec<&str> = config.databases.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, vec!["fd-lat-deu", "small", "hidden", "latin", "judy"]);
+    assert_eq!(config.exit, Some(2));
+
+    let lat_deu = &config.databases[0];
+    assert_eq!(lat_deu.kind, DatabaseKind::Files { data: "LAT_DEU.dict.dz".into(),
+            index: "LAT_DEU.index".into() });
+    assert_eq!(lat_deu.options, vec![("default_strategy".to_string(),
+            vec!["prefix".to_string()])]);
+    let small = &config.databases[1];
+    assert_eq!(small.short_name.as_deref(), Some("Small dictionary"));
+    assert_eq!(small.info.as_deref(), Some("A small dictionary\nfor testing"));
+    assert_eq!(small.access, vec![AccessRule::User("jane".into())]);
+    assert!(small.invisible && !lat_deu.invisible);
+    assert_eq!(config.databases[3].kind, DatabaseKind::Virtual {
+            members: vec!["fd-lat-deu".into(), "small".into()] });
+    assert_eq!(config.databases[4].kind, DatabaseKind::Plugin);
+}
+
+#[test]
+fn test_configured_databases_are_loaded() {
+    let dir = mk_config_dir("config", CONFIG);
+    let config = parse_config_file(dir.join("dictd.conf")).unwrap();
+    let mut collection = config.load().unwrap();
+    assert_eq!(collection.names(), vec!["fd-lat-deu", "small", "hidden", "latin"]);
+    assert!(collection.lookup("fd-lat-deu", "ferrugo").unwrap()[0].definition.contains("Rost"));
+
+    // the configured name replaces the short description of the database
+    let definitions = collection.lookup("*", "vinum").unwrap();
+    assert_eq!(definitions.last().unwrap().description, "Small dictionary");
+    assert_eq!(collection.open("small").unwrap().info().unwrap().info.as_deref(),
+            Some("A small dictionary\nfor testing"));
+
+    // databases after database_exit are only searched by name
+    assert!(definitions.iter().all(|d| d.database != "hidden"));
+    assert_eq!(collection.lookup("hidden", "vinum").unwrap().len(), 1);
+
+    // virtual databases search their members
+    let databases: Vec<String> = collection.lookup("latin", "vinum").unwrap().into_iter()
+            .map(|d| d.database).collect();
+    assert_eq!(databases, vec!["fd-lat-deu", "small"]);
+    cleanup(&dir);
+}
+
+#[test]
+fn test_configured_databases_can_be_opened_lazily() {
+    let config = "database broken { data missing.dict; index missing.index }\n\
+            database small { data small.dict; index small.index }\n";
+    let dir = mk_config_dir("config-lazy", config);
+    let config = parse_config_file(dir.join("dictd.conf")).unwrap();
+    assert!(config.load().is_err());
+    let mut collection = config.load_lazy().unwrap();
+    assert!(!collection.is_open("small"));
+    assert_eq!(collection.lookup("small", "vinum").unwrap().len(), 1);
+    assert!(collection.lookup("broken", "vinum").is_err());
+    cleanup(&dir);
+}
+
+#[test]
+fn test_included_files_are_resolved_against_the_including_file() {
+    let dir = mk_config_dir("config-include", "site site.txt\ninclude sub/databases.conf\n\
+            database_virtual all { database_list \"small, hidden\" }\n");
+    fs::create_dir(dir.join("sub")).unwrap();
+    for name in ["small.dict", "small.index"].iter() {
+        fs::copy(dir.join(name), dir.join("sub").join(name)).unwrap();
+    }
+    fs::write(dir.join("sub").join("databases.conf"),
+            "database small { data small.dict; index small.index }\ninclude exit.conf\n").unwrap();
+    fs::write(dir.join("sub").join("exit.conf"),
+            "database_exit\ndatabase hidden { data ../small.dict; index ../small.index }\n")
+            .unwrap();
+    let config = parse_config_file(dir.join("dictd.conf")).unwrap();
+    let names: Vec<&str> = config.databases.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, vec!["small", "hidden", "all"]);
+    assert_eq!(config.exit, Some(1));
+    assert_eq!(config.site.as_deref(), Some("site.txt"));
+    assert_eq!(config.databases[0].kind, DatabaseKind::Files {
+            data: dir.join("sub").join("small.dict"), index: dir.join("sub").join("small.index") });
+    let mut collection = config.load().unwrap();
+    assert_eq!(collection.lookup("*", "vinum").unwrap().len(), 1);
+    assert_eq!(collection.lookup("all", "vinum").unwrap().len(), 2);
+
+    // a file including itself is reported
+    fs::write(dir.join("loop.conf"), "include loop.conf\n").unwrap();
+    match parse_config_file(dir.join("loop.conf")) {
+        Err(DictError::InvalidFileFormat(explanation, _)) =>
+            assert!(explanation.contains("nested too deeply"), "{}", explanation),
+        other => panic!("expected an error, got {:?}", other),
+    }
+    assert!(parse_config_file(dir.join("missing.conf")).is_err());
+    cleanup(&dir);
+}
+
+#[test]
+fn test_invalid_configurations_are_reported() {
+    let line_of = |config: &str| match parse_config(config.as_bytes()) {
+        Err(DictError::InvalidFileFormat(explanation, _)) => explanation,
+        other => panic!("expected an error for {:?}, got {:?}", config, other),
+    };
+    assert!(line_of("database x { data x.dict }\n").contains("needs both data and index"));
+    assert!(line_of("\n\nfoo bar\n").starts_with("line 3 "));
+    assert!(line_of("database x {\n data x.dict\n").contains("missing }"));
+    assert!(line_of("}").contains("unexpected }"));
+    assert!(line_of("site \"unterminated\n").contains("unterminated string"));
+    assert!(line_of("access { permit all }").contains("invalid access rule"));
+    assert!(line_of("database_virtual v { name v }").contains("database_list"));
+}
@@ -1,19 +1,13 @@
 use std::fs::File;
 use std::io::{Cursor, Read};
-use std::path::PathBuf;
 
 use dict::*;
 use dict::dictreader::*;
 
-type StringFile = Cursor<String>;
+mod common;
+use common::{example_dictionary, get_asset_path, mk_workdir};
 
-fn get_asset_path(fname: &str) -> PathBuf {
-    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    path.push("tests");
-    path.push("assets");
-    path.push(fname);
-    path
-}
+type StringFile = Cursor<String>;
 
 // load test resource from tests/assets
 fn load_resource(name: &str) -> File {
@@ -225,6 +219,21 @@ fn test_that_definitions_wrapping_around_chunk_border_are_extracted_correctly()
     assert!(word.ends_with("herumtreiben\n"));
 }
 
+#[test]
+fn test_reads_ending_starting_and_crossing_chunk_borders_match_uncompressed_data() {
+    let border = dict::dictwriter::DICTZIP_CHUNK_LENGTH;
+    // two full chunks, so that the last read ends exactly at the end of the data
+    let data: String = (0..2 * border).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+    let mut compressed = Vec::new();
+    dict::dictwriter::write_dictzip(data.as_bytes(), &mut compressed).unwrap();
+    let mut dict = dictreader::DictReaderDz::new(Cursor::new(compressed)).unwrap();
+    for &(start, length) in [(border - 10, 10), (border, 10), (border - 10, 20), (border, 0),
+            (2 * border - 10, 10), (0, 2 * border)].iter() {
+        assert_eq!(dict.fetch_definition(start as u64, length as u64).unwrap(),
+                data[start..start + length], "offset: {}, length: {}", start, length);
+    }
+}
+
 #[test]
 fn test_files_with_comment_is_parsed_correctly() {
     // file in assets has no comment, so add one
@@ -280,3 +289,73 @@ fn test_that_seek_beyond_end_of_file_is_detected() {
     dict.fetch_definition(9999999999u64, 888u64).unwrap();
 }
 
+
+#[test]
+fn test_lookup_all_returns_every_homograph_in_index_order() {
+    let mut dict = example_dictionary();
+    let definitions = dict.lookup_all("abitum").unwrap();
+    assert_eq!(definitions.len(), 2);
+    assert!(definitions[0].contains("{abeo}"));
+    assert!(definitions[1].contains("{ambeo}"));
+    // lookup keeps returning the first definition
+    assert_eq!(dict.lookup("abitum").unwrap(), definitions[0]);
+}
+
+#[test]
+fn test_lookup_all_of_unknown_word_yields_error() {
+    let mut dict = example_dictionary();
+    assert!(dict.lookup_all("testtesttest").is_err());
+}
+
+#[test]
+fn test_headwords_are_listed_in_collation_order_without_meta_entries() {
+    let dict = example_dictionary();
+    let headwords = dict.headwords();
+    assert_eq!(&headwords[..2], ["a", "a puero"]);
+    assert!(headwords.windows(2).all(|w| dict.collation().compare(&w[0], &w[1]).is_le()));
+    assert!(!headwords.iter().any(|h| h.starts_with("00")));
+    assert!(dict.all_headwords().contains(&"00databaseshort".to_string()));
+    assert_eq!(headwords.iter().filter(|h| *h == "abitum").count(), 1);
+}
+
+#[test]
+fn test_entries_yield_every_definition_in_file_order() {
+    let mut dict = example_dictionary();
+    let entries: Vec<(String, String)> = dict.entries().collect::<Result<_, _>>().unwrap();
+    let abitum: Vec<&String> = entries.iter().filter(|e| e.0 == "abitum").map(|e| &e.1)
+        .collect();
+    assert_eq!(abitum.len(), 2);
+    assert!(abitum[0].contains("{abeo}"));
+    let (ref headword, ref definition) = entries[entries.len() / 2];
+    assert_eq!(*definition, dict.lookup(headword).unwrap());
+    assert!(!entries.iter().any(|e| e.0.starts_with("00")));
+    assert_eq!(dict.all_entries().count(), entries.len() + 6);
+}
+
+#[test]
+fn test_lookup_results_name_database_and_span() {
+    let mut dict = example_dictionary();
+    let results = dict.lookup_results("abitum").unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].headword, "abitum");
+    assert_eq!(results[0].database, "Latin - German FreeDict dictionary ver. 0.4");
+    assert_eq!(results[1].definition, dict.lookup_all("abitum").unwrap()[1]);
+    let mut reader = load_dict(get_asset_path("lat-deu.dict.dz")).unwrap();
+    assert_eq!(reader.fetch_definition(results[1].offset, results[1].length).unwrap(),
+            results[1].definition);
+}
+
+#[test]
+fn test_lenient_loading_skips_invalid_index_lines() {
+    let dir = mk_workdir("lenient");
+    let mut index = std::fs::read_to_string(get_asset_path("lat-deu.index")).unwrap();
+    index.push_str("broken line\n");
+    std::fs::write(dir.join("lat-deu.index"), index).unwrap();
+    assert!(load_dictionary_from_file(get_asset_path("lat-deu.dict.dz"),
+            dir.join("lat-deu.index")).is_err());
+    let (mut dict, warnings) = load_dictionary_from_file_lenient(
+            get_asset_path("lat-deu.dict.dz"), dir.join("lat-deu.index")).unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(dict.lookup("ferrugo").unwrap().contains("Rost"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
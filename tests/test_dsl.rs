@@ -0,0 +1,88 @@
+use std::fs;
+use std::io::Write;
+
+use dict::dsl::*;
+use dict::load_dictionary_from_file;
+
+mod common;
+use common::mk_workdir;
+
+static DSL: &str = "\u{feff}#NAME \"Latin - German\"
+#INDEX_LANGUAGE \"Latin\"
+#CONTENTS_LANGUAGE \"German\"
+
+mater
+matr{is}
+\t[m1][p]f[/p][/m]
+\t[m2][trn]Mutter[/trn][/m]
+\t[m2][ex][lang id=1031]~ familias[/ex] {{ father of the family? }}[/m]
+
+(ad)iuvo
+\t[m1]1. [trn]helfen[/trn][/m]
+\t[m1]2. [trn]unterstützen[/trn], see [ref]adiutor[/ref][/m]
+
+rosa{ rubra}
+  [trn]Rose[/trn] [s]rosa.wav[/s]
+";
+
+fn utf16le(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect()
+}
+
+#[test]
+fn test_cards_are_parsed_from_utf16() {
+    let (info, cards) = parse_dsl(&utf16le(DSL)[..]).unwrap();
+    assert_eq!(info.short_name.as_deref(), Some("Latin - German"));
+    assert_eq!(info.info.as_deref(), Some("Index language: Latin\nContents language: German"));
+    assert_eq!(cards.len(), 3);
+    assert_eq!(cards[0].headwords, vec!["mater", "matr"]);
+    assert_eq!(cards[0].titles, vec!["mater", "matris"]);
+    assert_eq!(cards[0].body.len(), 3);
+    assert_eq!(cards[1].headwords, vec!["adiuvo", "iuvo"]);
+    assert_eq!(cards[2].titles, vec!["rosa rubra"]);
+}
+
+#[test]
+fn test_utf8_and_utf16_give_the_same_cards() {
+    let from_utf8 = parse_dsl(DSL.as_bytes()).unwrap();
+    let without_bom = DSL.trim_start_matches('\u{feff}');
+    assert_eq!(parse_dsl(without_bom.as_bytes()).unwrap(), from_utf8);
+    assert_eq!(parse_dsl(&utf16le(without_bom)[..]).unwrap(), from_utf8);
+    assert_eq!(decode(&[0xFE, 0xFF, 0, b'#']).unwrap(), "#");
+    assert!(decode(&[0xFF, 0xFE, 0x00, 0xD8]).is_err());
+}
+
+#[test]
+fn test_cards_are_rendered_to_plain_text() {
+    let (_, cards) = parse_dsl(DSL.as_bytes()).unwrap();
+    assert_eq!(render(&cards[0]), "mater, matris\n f\n  Mutter\n  mater familias\n");
+    assert_eq!(render(&cards[1]),
+            "(ad)iuvo\n 1. helfen\n 2. unterstützen, see {adiutor}\n");
+    assert_eq!(render(&cards[2]), "rosa rubra\n Rose\n");
+}
+
+#[test]
+fn test_body_without_headword_is_an_error() {
+    assert!(parse_dsl("#NAME \"x\"\n\tbody\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_dsl_files_can_be_converted_and_loaded() {
+    let dir = mk_workdir("dsl");
+    let mut encoder = flate2::write::GzEncoder::new(
+            fs::File::create(dir.join("lat-deu.dsl.dz")).unwrap(), flate2::Compression::best());
+    encoder.write_all(&utf16le(DSL)).unwrap();
+    encoder.finish().unwrap();
+
+    let mut dict = load_dsl(dir.join("lat-deu.dsl.dz")).unwrap();
+    assert_eq!(dict.short_name().unwrap(), "Latin - German");
+    assert_eq!(dict.lookup("iuvo").unwrap(), dict.lookup("adiuvo").unwrap());
+    assert!(dict.lookup("matr").unwrap().contains("Mutter"));
+
+    convert_file(dir.join("lat-deu.dsl.dz"), dir.join("lat-deu.dict.dz"),
+            dir.join("lat-deu.index")).unwrap();
+    let mut dict = load_dictionary_from_file(dir.join("lat-deu.dict.dz"),
+            dir.join("lat-deu.index")).unwrap();
+    assert_eq!(dict.lookup("rosa").unwrap(), "rosa rubra\n Rose\n");
+    fs::remove_dir_all(dir).unwrap();
+}
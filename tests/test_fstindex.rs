@@ -0,0 +1,144 @@
+#![cfg(feature = "fst")]
+
+use std::io::Cursor;
+use std::ops::Bound;
+
+use dict::fstindex::FstIndex;
+use dict::indexing::*;
+use dict::matching::MatchOptions;
+
+mod common;
+use common::{example_dictionary, get_asset_path};
+
+fn example_index() -> Index {
+    parse_index_from_file(get_asset_path("lat-deu.index")).unwrap()
+}
+
+#[test]
+fn test_exact_lookups_return_the_same_locations() {
+    let index = example_index();
+    let fst = FstIndex::from_index(&index).unwrap();
+    assert_eq!(fst.len(), index.len());
+    for (headword, locations) in &index {
+        assert_eq!(fst.get(headword).as_ref(), Some(locations), "headword: {}", headword);
+    }
+    assert!(fst.get("testtesttest").is_none());
+    assert!(!fst.contains("testtesttest"));
+}
+
+#[test]
+fn test_duplicates_and_original_headwords_survive() {
+    let mut index = parse_index(Cursor::new("a\tB\tc\na\tA\tB\nRosa\tC\tD")).unwrap();
+    normalize_index(&mut index, |w| w.to_lowercase());
+    let fst = FstIndex::from_index(&index).unwrap();
+    assert_eq!(fst.get("a").unwrap().len(), 2);
+    assert_eq!(fst.get("rosa").unwrap()[0].original.as_deref(), Some("Rosa"));
+    assert_eq!(fst.to_index(), index);
+}
+
+#[test]
+fn test_prefix_and_range_queries() {
+    let fst = FstIndex::from_index(&example_index()).unwrap();
+    let matches = fst.prefix("circumfer");
+    assert!(matches.contains(&"circumfero".to_string()));
+    assert!(matches.iter().all(|w| w.starts_with("circumfer")));
+
+    let range = fst.range(Bound::Included("abd"), Bound::Excluded("abe"));
+    assert_eq!(range, vec!["abdere", "abdidi", "abditum", "abdo", "abducere", "abduco"]);
+}
+
+#[test]
+fn test_fuzzy_queries_respect_distance() {
+    let mut index = Index::new();
+    for word in &["amo", "amor", "amō", "mater", "ama"] {
+        index.insert(word.to_string(), vec![Location::new(0, 1)]);
+    }
+    let fst = FstIndex::from_index(&index).unwrap();
+    assert_eq!(fst.fuzzy("amo", 0), vec!["amo"]);
+    assert_eq!(fst.fuzzy("amo", 1), vec!["ama", "amo", "amor", "amō"]);
+    assert_eq!(fst.fuzzy("mat", 2), vec!["ama", "mater"]);
+}
+
+#[test]
+fn test_index_can_be_saved_and_loaded() {
+    let index = example_index();
+    let fst = FstIndex::from_index(&index).unwrap();
+    let mut buffer = Vec::new();
+    fst.write_to(&mut buffer).unwrap();
+    let loaded = FstIndex::read_from(Cursor::new(buffer)).unwrap();
+    assert_eq!(loaded.to_index(), index);
+}
+
+#[test]
+fn test_loading_garbage_is_reported() {
+    assert!(FstIndex::read_from(Cursor::new(b"garbage".to_vec())).is_err());
+}
+
+#[test]
+fn test_invalid_references_to_locations_are_reported() {
+    let index = parse_index(Cursor::new("a\tB\tc\na\tA\tB")).unwrap();
+    let mut buffer = Vec::new();
+    FstIndex::from_index(&index).unwrap().write_to(&mut buffer).unwrap();
+    // drop the table of locations which could not be packed
+    let mut fst_length = [0u8; 8];
+    fst_length.copy_from_slice(&buffer[8..16]);
+    buffer.truncate(16 + u64::from_le_bytes(fst_length) as usize);
+    buffer.extend_from_slice(&0u64.to_le_bytes());
+    match FstIndex::read_from(Cursor::new(buffer)) {
+        Err(dict::errors::DictError::InvalidFileFormat(explanation, _)) =>
+            assert!(explanation.contains("missing locations")),
+        Err(e) => panic!("expected InvalidFileFormat, got {:?}", e),
+        Ok(_) => panic!("invalid FST index was loaded"),
+    }
+}
+
+#[test]
+fn test_dictionary_can_use_a_compact_index() {
+    let mut dict = example_dictionary();
+    let headwords = dict.headwords();
+    dict.compact_index().unwrap();
+    assert!(dict.lookup("ferrugo").unwrap().contains("Rost"));
+    assert_eq!(dict.lookup_all("abitum").unwrap().len(), 2);
+    assert!(dict.lookup("testtesttest").is_err());
+    assert_eq!(dict.headwords(), headwords);
+    assert_eq!(dict.short_name().unwrap(), "Latin - German FreeDict dictionary ver. 0.4");
+    let options = dict::matching::MatchOptions::default();
+    assert!(dict.match_words("prefix", "circumfer", &options).unwrap()
+            .contains(&"circumfero".to_string()));
+    dict.set_normalizer(dict::normalizer::Normalizer::default());
+    assert!(dict.contains("Ferrugo"));
+}
+
+#[test]
+fn test_bounded_queries_stop_at_the_limit() {
+    let fst = FstIndex::from_index(&example_index()).unwrap();
+    let options = MatchOptions { limit: 2, ..MatchOptions::default() };
+    assert_eq!(fst.prefix_matches("abd", &options).unwrap(), vec!["abdere", "abdidi"]);
+    assert_eq!(fst.fuzzy_matches("abdo", 1, &options).unwrap(), fst.fuzzy("abdo", 1)[..2]);
+    assert_eq!(fst.keys().len(), fst.len());
+}
+
+#[test]
+fn test_compact_index_matches_like_the_hash_map() {
+    let dict = example_dictionary();
+    let mut compact = example_dictionary();
+    compact.compact_index().unwrap();
+    let options = MatchOptions::default();
+    for (strategy, query) in &[("exact", "abdo"), ("prefix", "abd"), ("lev", "amo"),
+            ("suffix", "ere")] {
+        assert_eq!(compact.match_words(strategy, query, &options).unwrap(),
+                dict.match_words(strategy, query, &options).unwrap(), "strategy {}", strategy);
+    }
+}
+
+#[test]
+fn test_compact_index_can_be_validated_and_searched() {
+    let mut dict = example_dictionary();
+    let issues = dict.validate().unwrap();
+    let hits = dict.search_definitions("Rost").unwrap();
+    let mut compact = example_dictionary();
+    compact.compact_index().unwrap();
+    assert_eq!(compact.validate().unwrap(), issues);
+    assert_eq!(compact.search_definitions("Rost").unwrap(), hits);
+    assert_eq!(compact.entries().count(), dict.entries().count());
+}
@@ -0,0 +1,64 @@
+use dict::fulltext::DefinitionIndex;
+
+mod common;
+use common::{example_dictionary, mk_dict};
+
+#[test]
+fn test_translation_finds_headword() {
+    let mut dict = example_dictionary();
+    let hits = dict.search_definitions("Rost").unwrap();
+    assert_eq!(hits[0].headwords, vec!["ferrugo"]);
+    // queries are lowercased like the definitions
+    assert_eq!(dict.search_definitions("rost").unwrap(), hits);
+}
+
+#[test]
+fn test_meta_entries_are_not_searched() {
+    let mut dict = example_dictionary();
+    assert!(dict.search_definitions("FreeDict").unwrap().is_empty());
+}
+
+#[test]
+fn test_definitions_with_more_and_rarer_terms_rank_higher() {
+    // "rosa" occurs once in each definition, "rubra" only in the second
+    let mut dict = mk_dict("rosa alba\nrosa rubra\nvinum\n", "alba\tA\tK\nrubra\tK\tL\nvinum\tV\tG");
+    let hits = dict.search_definitions("Rosa Rubra").unwrap();
+    let headwords: Vec<&str> = hits.iter().map(|h| h.headwords[0].as_str()).collect();
+    assert_eq!(headwords, vec!["rubra", "alba"]);
+    assert!(hits[0].score > hits[1].score);
+    assert!(dict.search_definitions("aqua").unwrap().is_empty());
+}
+
+#[test]
+fn test_headwords_sharing_a_definition_are_grouped() {
+    let mut dict = mk_dict("mater, matris: Mutter", "mater\tA\tV\nmatris\tA\tV");
+    let hits = dict.search_definitions("mutter").unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].headwords, vec!["mater", "matris"]);
+}
+
+#[test]
+fn test_saved_index_gives_same_results() {
+    let mut dict = example_dictionary();
+    let mut data = Vec::new();
+    dict.definition_index().unwrap().write_to(&mut data).unwrap();
+    let loaded = DefinitionIndex::read_from(&data[..]).unwrap();
+    assert_eq!(&loaded, dict.definition_index().unwrap());
+    assert_eq!(loaded.search("verbergen"), dict.search_definitions("verbergen").unwrap());
+}
+
+#[test]
+fn test_loading_something_else_fails() {
+    assert!(DefinitionIndex::read_from(&b"DIXC\x01\x00\x00\x00"[..]).is_err());
+}
+
+#[test]
+fn test_index_of_another_dictionary_is_refused() {
+    let mut old = mk_dict("rosa alba\nvinum\n", "alba\tA\tK\nvinum\tK\tG");
+    let index = old.definition_index().unwrap().clone();
+    let mut same = mk_dict("rosa alba\nvinum\n", "alba\tA\tK\nvinum\tK\tG");
+    assert!(same.set_definition_index(index.clone()).is_ok());
+    let mut changed = mk_dict("rosa rubra\nvinum\n", "rubra\tA\tL\nvinum\tL\tG");
+    assert!(changed.set_definition_index(index).is_err());
+    assert_eq!(changed.search_definitions("rubra").unwrap()[0].headwords, vec!["rubra"]);
+}
@@ -33,6 +33,7 @@ fn test_that_slash_and_plus_get_correct_number() {
 }
 
 #[test]
+#[allow(non_fmt_panics)]
 fn test_that_unknown_characters_return_error() {
     assert!(dict::indexing::decode_number("*").is_err(), 99999);
 }
@@ -73,17 +74,28 @@ fn test_only_one_tab_causes_panic() {
     parse_index(*mk_file("only one\t(tab) character")).unwrap();
 }
 
+fn loc(offset: u64, length: u64) -> Location {
+    Location::new(offset, length)
+}
+
 #[test]
 fn test_that_normal_entry_works() {
     let index = parse_index(*mk_file("word\toffset\tlength")).unwrap();
-    assert_eq!(*(index.get("word").unwrap()), (43478075309, 40242121569));
+    assert_eq!(*(index.get("word").unwrap()), vec![loc(43478075309, 40242121569)]);
 }
 
 #[test]
 fn test_that_two_entries_are_parsed() {
     let index = parse_index(*mk_file("word\toffset\tlength\nanother\ta0b\tc")).unwrap();
-    assert_eq!(*(index.get("word").unwrap()), (43478075309, 40242121569));
-    assert_eq!(*(index.get("another").unwrap()), (109851, 28));
+    assert_eq!(*(index.get("word").unwrap()), vec![loc(43478075309, 40242121569)]);
+    assert_eq!(*(index.get("another").unwrap()), vec![loc(109851, 28)]);
+}
+
+#[test]
+fn test_that_duplicate_headwords_keep_all_locations_in_order() {
+    let index = parse_index(*mk_file("a\tB\tc\nb\tC\tD\na\tA\tB")).unwrap();
+    assert_eq!(*(index.get("a").unwrap()), vec![loc(1, 28), loc(0, 1)]);
+    assert_eq!(*(index.get("b").unwrap()), vec![loc(2, 3)]);
 }
 
 #[test]
@@ -92,3 +104,121 @@ fn test_that_number_parsing_errors_are_propagated() {
     parse_index(*mk_file("valid word\tinvalid_offset\tDA")).unwrap();
 }
 
+
+#[test]
+fn test_normalising_an_index_keeps_original_headwords() {
+    let mut index = parse_index(*mk_file("Rosa\tB\tc\nrosa\tA\tB\nmater\tC\tD")).unwrap();
+    normalize_index(&mut index, |w| w.to_lowercase());
+    let mut original = loc(1, 28);
+    original.original = Some("Rosa".into());
+    assert_eq!(*(index.get("rosa").unwrap()), vec![loc(0, 1), original]);
+    assert_eq!(*(index.get("mater").unwrap()), vec![loc(2, 3)]);
+    assert!(!index.contains_key("Rosa"));
+}
+
+#[test]
+fn test_normalising_an_index_keeps_the_order_of_homographs() {
+    let mut index = parse_index(*mk_file("Rosa\tC\tD\nRosa\tA\tB")).unwrap();
+    normalize_index(&mut index, |w| w.to_lowercase());
+    let offsets: Vec<u64> = index["rosa"].iter().map(|l| l.offset).collect();
+    assert_eq!(offsets, vec![2, 0]);
+}
+
+#[test]
+fn test_normalising_an_index_again_starts_from_original_headwords() {
+    let mut index = parse_index(*mk_file("Rosa\tB\tc")).unwrap();
+    normalize_index(&mut index, |w| w.to_lowercase());
+    normalize_index(&mut index, |w| w.to_uppercase());
+    let mut original = loc(1, 28);
+    original.original = Some("Rosa".into());
+    assert_eq!(*(index.get("ROSA").unwrap()), vec![original]);
+    assert_eq!(index.len(), 1);
+}
+
+#[test]
+fn test_that_fourth_column_is_kept_as_original_headword() {
+    let index = parse_index(*mk_file("rosa\tB\tc\tRosa\nmater\tC\tD\tmater\nfoo\tA\tB\t")).unwrap();
+    let mut original = loc(1, 28);
+    original.original = Some("Rosa".into());
+    assert_eq!(*(index.get("rosa").unwrap()), vec![original]);
+    // identical or empty original spellings are not stored
+    assert_eq!(*(index.get("mater").unwrap()), vec![loc(2, 3)]);
+    assert_eq!(*(index.get("foo").unwrap()), vec![loc(0, 1)]);
+}
+
+#[test]
+fn test_index_entries_report_line_number_and_text() {
+    let entries: Vec<_> = IndexEntries::new(*mk_file("a\tB\tc\tA\r\nbroken\n")).collect();
+    assert_eq!(entries.len(), 2);
+    let first = entries[0].as_ref().unwrap();
+    assert_eq!((first.headword.as_str(), first.line_number, first.line.as_str()),
+               ("a", 1, "a\tB\tc\tA"));
+    assert_eq!(first.location.original, Some("A".into()));
+    match entries[1] {
+        Err(dict::errors::DictError::InvalidIndexLine(2, ref line, ref err)) => {
+            assert_eq!(line, "broken");
+            // the wrapped error is the one parse_index reports, with a 0-based line number
+            assert!(matches!(**err, dict::errors::DictError::MissingColumnInIndex(1)));
+        },
+        ref other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_invalid_characters_report_their_line() {
+    let error = IndexEntries::new(*mk_file("a\tB\tc\nb\tB*\tc")).nth(1).unwrap().unwrap_err();
+    match error {
+        dict::errors::DictError::InvalidIndexLine(2, _, err) => assert!(matches!(*err,
+                dict::errors::DictError::InvalidCharacter('*', None, Some(0)))),
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+#[test]
+fn test_strict_parsing_reports_errors_unwrapped() {
+    match parse_index(*mk_file("a\tB\tc\nbroken")) {
+        Err(dict::errors::DictError::MissingColumnInIndex(1)) => (),
+        other => panic!("expected MissingColumnInIndex(1), got {:?}", other),
+    }
+    match parse_index(*mk_file("a\tB\tc\nb\tB*\tc")) {
+        Err(dict::errors::DictError::InvalidCharacter('*', None, Some(0))) => (),
+        other => panic!("expected InvalidCharacter, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_lenient_parsing_skips_invalid_lines() {
+    let input: &[u8] = b"a\tB\tc\nbroken\nb\t\xff\tc\nc\tC\tD\n";
+    let (index, warnings) = parse_index_lenient(input).unwrap();
+    assert_eq!(index.len(), 2);
+    assert_eq!(*(index.get("c").unwrap()), vec![loc(2, 3)]);
+    let lines: Vec<usize> = warnings.iter().map(|w| match *w {
+        dict::errors::DictError::InvalidIndexLine(line, _, _) => line,
+        _ => panic!("unexpected warning {:?}", w),
+    }).collect();
+    assert_eq!(lines, vec![2, 3]);
+}
+
+#[test]
+fn test_too_large_numbers_are_reported() {
+    assert_eq!(decode_number("P//////////").unwrap(), u64::MAX);
+    assert!(decode_number("Q//////////").is_err());
+    // leading zeros don't count
+    assert_eq!(decode_number("AAAAAAAAAAAAAAB").unwrap(), 1);
+    assert!(decode_number("BAAAAAAAAAAAAAA").is_err());
+    let (index, warnings) = parse_index_lenient(&b"a\tB\tc\nb\tBAAAAAAAAAAAAAA\tc\n"[..])
+        .unwrap();
+    assert_eq!(index.len(), 1);
+    match warnings[..] {
+        [dict::errors::DictError::InvalidIndexLine(2, _, _)] => (),
+        _ => panic!("unexpected warnings {:?}", warnings),
+    }
+}
+
+#[test]
+fn test_encoded_numbers_can_be_decoded() {
+    for number in [0, 1, 63, 64, 226751, 14546358, u32::MAX as u64].iter() {
+        assert_eq!(decode_number(&encode_number(*number)).unwrap(), *number);
+    }
+    assert_eq!(encode_number(0), "A");
+}
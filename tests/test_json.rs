@@ -0,0 +1,87 @@
+#![cfg(feature = "serde")]
+
+use std::fs;
+
+use dict::builder::DictionaryBuilder;
+use dict::json::*;
+use dict::load_dictionary_from_file;
+
+mod common;
+use common::{get_asset_path, mk_builder, mk_workdir};
+
+/// builder with an entry with an alternative headword
+fn mk_latin_german() -> DictionaryBuilder {
+    let mut builder = mk_builder(Some("Latin - German"), &[]);
+    builder.add_entry(&["mater", "matris"], "mater\n Mutter").unwrap();
+    builder.add_entry(&["rosa"], "rosa\n Rose").unwrap();
+    builder
+}
+
+#[test]
+fn test_records_start_with_metadata() {
+    let records = records(&mut mk_latin_german().build().unwrap()).unwrap();
+    assert_eq!(records.len(), 3);
+    match records[0] {
+        Record::Metadata(ref info) => assert_eq!(info.short_name.as_deref(),
+                Some("Latin - German")),
+        _ => panic!("first record is not the metadata: {:?}", records[0]),
+    }
+    let mater = records.iter().find_map(|r| match r {
+        Record::Entry(e) if e.headword == "mater" => Some(e),
+        _ => None,
+    }).unwrap();
+    assert_eq!(mater.alternates, vec!["matris"]);
+    assert_eq!(mater.definition, "mater\n Mutter\n");
+    assert_eq!(mater.length, 14);
+}
+
+#[test]
+fn test_json_lines_have_one_record_per_line() {
+    let mut output = Vec::new();
+    write_jsonl(&mut mk_latin_german().build().unwrap(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), 3);
+    assert!(output.starts_with("{\"type\":\"metadata\""));
+    assert!(output.contains("\"headword\":\"rosa\",\"alternates\":[],\
+            \"definition\":\"rosa\\n Rose\\n\""));
+}
+
+#[test]
+fn test_records_can_be_read_back() {
+    let mut dict = mk_latin_german().build().unwrap();
+    let mut jsonl = Vec::new();
+    write_jsonl(&mut dict, &mut jsonl).unwrap();
+    let mut json = Vec::new();
+    write_json(&mut dict, &mut json).unwrap();
+    let expected = records(&mut dict).unwrap();
+    assert_eq!(read_jsonl(&jsonl[..]).unwrap(), expected);
+    assert_eq!(read_json(&json[..]).unwrap(), expected);
+
+    let minimal = "{\"type\":\"entry\",\"headword\":\"vinum\",\"definition\":\"Wein\"}\n\n";
+    let records = read_jsonl(minimal.as_bytes()).unwrap();
+    let mut dict = to_builder(&records).unwrap().build().unwrap();
+    assert_eq!(dict.lookup("vinum").unwrap(), "Wein\n");
+
+    let err = read_jsonl("{\"type\":\"entry\"}\n".as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("line 1"), "{}", err);
+}
+
+#[test]
+fn test_lat_deu_survives_a_round_trip() {
+    let mut original = load_dictionary_from_file(get_asset_path("lat-deu.dict.dz"),
+            get_asset_path("lat-deu.index")).unwrap();
+    let dir = mk_workdir("json");
+    export_file(&mut original, dir.join("lat-deu.jsonl")).unwrap();
+    convert_file(dir.join("lat-deu.jsonl"), dir.join("lat-deu.dict.dz"),
+            dir.join("lat-deu.index")).unwrap();
+    let mut imported = load_dictionary_from_file(dir.join("lat-deu.dict.dz"),
+            dir.join("lat-deu.index")).unwrap();
+    assert_eq!(imported.info().unwrap(), original.info().unwrap());
+    assert_eq!(imported.headwords(), original.headwords());
+    assert_eq!(imported.lookup_all("abitum").unwrap(), original.lookup_all("abitum").unwrap());
+
+    export_file(&mut original, dir.join("lat-deu.json")).unwrap();
+    let mut loaded = load_file(dir.join("lat-deu.json")).unwrap();
+    assert_eq!(loaded.lookup("ferrugo").unwrap(), original.lookup("ferrugo").unwrap());
+    fs::remove_dir_all(dir).unwrap();
+}
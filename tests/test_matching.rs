@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use dict::*;
+use dict::matching::MatchOptions;
+
+mod common;
+use common::example_dictionary;
+
+#[test]
+fn test_regex_finds_all_matching_headwords_in_order() {
+    let dict = example_dictionary();
+    let matches = dict.match_regex("^am[ao]$", &MatchOptions::default()).unwrap();
+    assert_eq!(matches, vec!["amo".to_string()]);
+}
+
+#[test]
+fn test_regex_is_not_anchored() {
+    let dict = example_dictionary();
+    let matches = dict.match_regex("tio$", &MatchOptions::default()).unwrap();
+    assert!(!matches.is_empty());
+    assert!(matches.iter().all(|w| w.ends_with("tio")));
+    let mut sorted = matches.clone();
+    sorted.sort();
+    assert_eq!(matches, sorted);
+}
+
+#[test]
+fn test_posix_bracket_expressions_are_supported() {
+    let dict = example_dictionary();
+    let matches = dict.match_regex("^[[:alpha:]]+ [[:alpha:]]+$", &MatchOptions::default())
+        .unwrap();
+    assert!(matches.contains(&"a puero".to_string()));
+}
+
+#[test]
+fn test_invalid_regex_is_reported() {
+    let dict = example_dictionary();
+    match dict.match_regex("(unclosed", &MatchOptions::default()) {
+        Err(errors::DictError::InvalidPattern(_)) => (),
+        other => panic!("expected InvalidPattern, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_glob_matches_whole_headword() {
+    let dict = example_dictionary();
+    let matches = dict.match_glob("ab?o", &MatchOptions::default()).unwrap();
+    assert_eq!(matches, vec!["abdo".to_string(), "abeo".to_string()]);
+    assert!(dict.match_glob("circumfer*", &MatchOptions::default()).unwrap()
+            .contains(&"circumfero".to_string()));
+}
+
+#[test]
+fn test_number_of_matches_is_limited() {
+    let dict = example_dictionary();
+    let options = MatchOptions { limit: 3, ..MatchOptions::default() };
+    let matches = dict.match_glob("a*", &options).unwrap();
+    assert_eq!(matches.len(), 3);
+    assert_eq!(matches[0], "a");
+}
+
+#[test]
+fn test_exceeding_the_time_limit_is_reported() {
+    let dict = example_dictionary();
+    let options = MatchOptions { timeout: Duration::from_secs(0), ..MatchOptions::default() };
+    match dict.match_regex(".", &options) {
+        Err(errors::DictError::Timeout) => (),
+        other => panic!("expected Timeout, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_suffix_finds_all_headwords_with_ending() {
+    let dict = example_dictionary();
+    let matches = dict.match_suffix("tio", &MatchOptions::default()).unwrap();
+    assert_eq!(matches, dict.match_regex("tio$", &MatchOptions::default()).unwrap());
+    assert!(!matches.is_empty());
+}
+
+#[test]
+fn test_suffix_without_matches_yields_empty_list() {
+    let dict = example_dictionary();
+    assert!(dict.match_suffix("qqq", &MatchOptions::default()).unwrap().is_empty());
+}
+
+#[test]
+fn test_substring_matches_equal_regex_matches() {
+    let dict = example_dictionary();
+    for query in &["o", "pu", "ferr", "a pu", "circumfero"] {
+        let matches = dict.match_substring(query, &MatchOptions::default()).unwrap();
+        let expected = dict.match_regex(&regex::escape(query), &MatchOptions::default()).unwrap();
+        assert_eq!(matches, expected, "query: {}", query);
+    }
+}
+
+#[test]
+fn test_number_of_substring_matches_is_limited() {
+    let dict = example_dictionary();
+    let options = MatchOptions { limit: 2, ..MatchOptions::default() };
+    assert_eq!(dict.match_substring("a", &options).unwrap().len(), 2);
+}
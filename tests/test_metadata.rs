@@ -0,0 +1,37 @@
+use dict::*;
+use dict::metadata::DatabaseInfo;
+
+mod common;
+use common::{get_asset_path, mk_dict};
+
+#[test]
+fn test_info_of_freedict_database() {
+    let mut dict = load_dictionary_from_file(get_asset_path("lat-deu.dict.dz"),
+            get_asset_path("lat-deu.index")).unwrap();
+    let info = dict.info().unwrap();
+    assert_eq!(info.short_name.as_deref(), Some("Latin - German FreeDict dictionary ver. 0.4"));
+    assert!(info.info.unwrap().starts_with("Latin - German FreeDict dictionary\n\nconverted by"));
+    assert_eq!(info.url.as_deref(), Some("unknown"));
+    assert!(info.utf8);
+    assert!(!info.eight_bit && !info.allchars && !info.case_sensitive);
+    assert_eq!(info.alphabet.as_deref(), Some(" abcdefghilmnopqrstuvxyz\u{e9}"));
+    assert_eq!(info.dictfmt_version.as_deref(), Some("1.12.1"));
+}
+
+#[test]
+fn test_dashed_headwords_are_stripped_from_definitions() {
+    let text = "00-database-short\n  Test\n00-database-allchars\n";
+    let index = "00-database-short\tA\tY\n00-database-allchars\tY\tV";
+    let mut dict = mk_dict(text, index);
+    assert_eq!(dict.short_name().unwrap(), "Test");
+    let info = dict.info().unwrap();
+    assert_eq!(info, DatabaseInfo { short_name: Some("Test".into()), allchars: true,
+            ..DatabaseInfo::default() });
+}
+
+#[test]
+fn test_missing_short_name_is_reported() {
+    let mut dict = mk_dict("foo", "foo\tA\tD");
+    assert!(dict.short_name().is_err());
+    assert_eq!(dict.info().unwrap(), DatabaseInfo::default());
+}
@@ -0,0 +1,40 @@
+use dict::matching::MatchOptions;
+use dict::normalizer::{Normalizer, UnicodeForm};
+
+mod common;
+use common::mk_dict;
+
+#[test]
+fn test_decomposed_headwords_are_found_with_composed_query() {
+    let mut dict = mk_dict("evidence", "e\u{301}vidence\tA\tI");
+    assert_eq!(dict.lookup("\u{e9}vidence").unwrap(), "evidence");
+    assert_eq!(dict.lookup("e\u{301}vidence").unwrap(), "evidence");
+    assert!(dict.lookup("evidence").is_err());
+}
+
+#[test]
+fn test_folding_diacritics_finds_marked_headwords() {
+    let mut dict = mk_dict("amo, amare", "am\u{14d}\tA\tK");
+    assert!(dict.lookup("amo").is_err());
+    dict.set_normalizer(Normalizer { form: UnicodeForm::Nfc, fold_diacritics: true });
+    assert_eq!(dict.lookup("amo").unwrap(), "amo, amare");
+    assert_eq!(dict.lookup("am\u{14d}").unwrap(), "amo, amare");
+}
+
+#[test]
+fn test_matches_return_original_headwords() {
+    let mut dict = mk_dict("amo, amareamor", "am\u{14d}\tA\tK\namor\tK\tE");
+    dict.set_normalizer(Normalizer { form: UnicodeForm::Nfc, fold_diacritics: true });
+    let matches = dict.match_words("prefix", "amo", &MatchOptions::default()).unwrap();
+    assert_eq!(matches, vec!["am\u{14d}", "amor"]);
+}
+
+#[test]
+fn test_switching_back_restores_distinction() {
+    let mut dict = mk_dict("amo, amareamor", "am\u{14d}\tA\tK\namo\tK\tE");
+    dict.set_normalizer(Normalizer { form: UnicodeForm::Nfc, fold_diacritics: true });
+    assert_eq!(dict.lookup_all("amo").unwrap().len(), 2);
+    dict.set_normalizer(Normalizer::default());
+    assert_eq!(dict.lookup_all("amo").unwrap(), vec!["amor"]);
+    assert_eq!(dict.lookup_all("am\u{14d}").unwrap(), vec!["amo, amare"]);
+}
@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::PathBuf;
+
+use dict::scan::*;
+
+mod common;
+use common::{cleanup, get_asset_path, mk_builder, mk_workdir};
+
+// create a directory with two valid, one broken and two incomplete databases
+fn mk_dictd_dir(name: &str) -> PathBuf {
+    let dir = mk_workdir(name);
+    fs::create_dir(dir.join("subdir.index")).unwrap();
+    fs::copy(get_asset_path("lat-deu.index"), dir.join("freedict-lat-deu.index")).unwrap();
+    fs::copy(get_asset_path("lat-deu.dict.dz"), dir.join("freedict-lat-deu.dict.dz")).unwrap();
+    mk_builder(None, &[("vinum", "vinum\n Wein")])
+        .write_files(dir.join("small.dict"), dir.join("small.index")).unwrap();
+    fs::write(dir.join("broken.index"), "vinum\tA\n").unwrap();
+    fs::write(dir.join("broken.dict"), "vinum\n").unwrap();
+    fs::write(dir.join("lonely.index"), "vinum\tA\tB\n").unwrap();
+    fs::write(dir.join("alone.dict.dz"), "").unwrap();
+    fs::write(dir.join("README"), "").unwrap();
+    dir
+}
+
+fn file_names(paths: &[PathBuf]) -> Vec<&str> {
+    paths.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect()
+}
+
+#[test]
+fn test_databases_are_paired_by_base_name() {
+    let dir = mk_dictd_dir("scan");
+    let mut report = scan_directory(&dir).unwrap();
+    assert_eq!(report.collection.names(), vec!["freedict-lat-deu", "small"]);
+    assert!(report.collection.is_open("small"));
+    assert_eq!(file_names(&report.orphans), vec!["alone.dict.dz", "lonely.index"]);
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.errors[0].0.ends_with("broken.index"));
+    let definitions = report.collection.lookup("*", "vinum").unwrap();
+    assert_eq!(definitions.len(), 2);
+    assert_eq!(definitions[0].database, "freedict-lat-deu");
+    assert_eq!(definitions[1].database, "small");
+    cleanup(&dir);
+}
+
+#[test]
+fn test_compressed_dict_files_are_preferred() {
+    let dir = mk_dictd_dir("scan-preferred");
+    fs::write(dir.join("freedict-lat-deu.dict"), "invalid").unwrap();
+    let mut report = scan_directory(&dir).unwrap();
+    assert!(report.orphans.iter().all(|p| !p.ends_with("freedict-lat-deu.dict")));
+    let dictionary = report.collection.open("freedict-lat-deu").unwrap();
+    assert!(dictionary.lookup("ferrugo").unwrap().contains("Rost"));
+    cleanup(&dir);
+}
+
+#[test]
+fn test_lazy_scan_opens_databases_on_first_use() {
+    let dir = mk_dictd_dir("scan-lazy");
+    let mut report = scan_directory_lazy(&dir).unwrap();
+    assert_eq!(report.collection.names(), vec!["broken", "freedict-lat-deu", "small"]);
+    assert!(report.errors.is_empty());
+    assert!(!report.collection.is_open("small"));
+    assert!(report.collection.lookup("small", "vinum").is_ok());
+    assert!(report.collection.is_open("small"));
+    assert!(!report.collection.is_open("freedict-lat-deu"));
+    assert!(report.collection.failures().is_empty());
+    // the broken database is skipped and remembered
+    assert_eq!(report.collection.lookup("*", "vinum").unwrap().last().unwrap().database, "small");
+    let failures = report.collection.failures();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].0, "broken");
+    assert!(report.collection.lookup("broken", "vinum").is_err());
+    cleanup(&dir);
+}
+
+#[test]
+fn test_missing_directory_is_an_error() {
+    assert!(scan_directory("/nonexistent/libdict/dictd").is_err());
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "serde")]
+
+use std::io::Cursor;
+
+use dict::errors::DictError;
+use dict::indexing::{parse_index, IndexEntries, Index, Location};
+use dict::*;
+
+mod common;
+use common::get_asset_path;
+
+#[test]
+fn test_errors_are_serialised_with_kind_and_message() {
+    let error = DictError::WordNotFound("rosa".into());
+    assert_eq!(serde_json::to_string(&error).unwrap(),
+            r#"{"kind":"WordNotFound","message":"Word not found: rosa"}"#);
+    let error = parse_index(Cursor::new("rosa\tA\n")).unwrap_err();
+    assert_eq!(serde_json::to_value(&error).unwrap()["kind"], "MissingColumnInIndex");
+}
+
+#[test]
+fn test_index_survives_a_round_trip() {
+    let index = parse_index(Cursor::new("mater\tA\tP\nmatris\tA\tP\tMatris\n")).unwrap();
+    let json = serde_json::to_string(&index).unwrap();
+    let restored: Index = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, index);
+    let location: Location = serde_json::from_str(r#"{"offset":1,"length":2}"#).unwrap();
+    assert_eq!(location, Location::new(1, 2));
+
+    let entry = IndexEntries::new(Cursor::new("mater\tA\tP\n")).next().unwrap().unwrap();
+    let json = serde_json::to_value(&entry).unwrap();
+    assert_eq!(json["line_number"], 1);
+    assert_eq!(serde_json::from_value::<indexing::IndexEntry>(json).unwrap(), entry);
+}
+
+#[test]
+fn test_metadata_and_lookup_results_are_serialised() {
+    let mut dict = load_dictionary_from_file(get_asset_path("lat-deu.dict.dz"),
+            get_asset_path("lat-deu.index")).unwrap();
+    let info = dict.info().unwrap();
+    let json = serde_json::to_string(&info).unwrap();
+    assert_eq!(serde_json::from_str::<metadata::DatabaseInfo>(&json).unwrap(), info);
+    let partial: metadata::DatabaseInfo = serde_json::from_str(r#"{"utf8":true}"#).unwrap();
+    assert!(partial.utf8 && partial.short_name.is_none());
+
+    let results = dict.lookup_results("ferrugo").unwrap();
+    let json = serde_json::to_value(&results).unwrap();
+    assert_eq!(json[0]["database"], "Latin - German FreeDict dictionary ver. 0.4");
+    assert!(json[0]["definition"].as_str().unwrap().contains("Rost"));
+    assert_eq!(serde_json::from_value::<Vec<LookupResult>>(json).unwrap(), results);
+}
@@ -0,0 +1,211 @@
+use std::fs;
+use std::io::Write;
+use std::cmp::Ordering;
+use std::path::Path;
+
+use dict::builder::DictionaryBuilder;
+use dict::dictwriter::write_dictzip;
+use dict::metadata::DatabaseInfo;
+use dict::stardict::*;
+
+mod common;
+use common::{get_asset_path, mk_workdir};
+
+// write a StarDict dictionary with the given (already encoded) definitions
+fn write_stardict(dir: &Path, ifo: &str, definitions: &[(&str, &[u8])], synonyms: &[(&str, u32)],
+        offset_bits: u32, compress: bool) {
+    let (mut data, mut idx, mut syn) = (Vec::new(), Vec::new(), Vec::new());
+    for (word, definition) in definitions {
+        idx.extend_from_slice(word.as_bytes());
+        idx.push(0);
+        match offset_bits {
+            64 => idx.extend_from_slice(&(data.len() as u64).to_be_bytes()),
+            _ => idx.extend_from_slice(&(data.len() as u32).to_be_bytes()),
+        }
+        idx.extend_from_slice(&(definition.len() as u32).to_be_bytes());
+        data.extend_from_slice(definition);
+    }
+    for (word, number) in synonyms {
+        syn.extend_from_slice(word.as_bytes());
+        syn.push(0);
+        syn.extend_from_slice(&number.to_be_bytes());
+    }
+    fs::write(dir.join("test.ifo"), format!("StarDict's dict ifo file\nversion=3.0.0\n\
+            wordcount={}\nidxfilesize={}\nidxoffsetbits={}\n{}", definitions.len(), idx.len(),
+            offset_bits, ifo)).unwrap();
+    if compress {
+        let mut encoder = flate2::write::GzEncoder::new(
+                fs::File::create(dir.join("test.idx.gz")).unwrap(), flate2::Compression::best());
+        encoder.write_all(&idx).unwrap();
+        encoder.finish().unwrap();
+        write_dictzip(&data, fs::File::create(dir.join("test.dict.dz")).unwrap()).unwrap();
+    } else {
+        fs::write(dir.join("test.idx"), idx).unwrap();
+        fs::write(dir.join("test.dict"), data).unwrap();
+    }
+    if !synonyms.is_empty() {
+        fs::write(dir.join("test.syn"), syn).unwrap();
+    }
+}
+
+#[test]
+fn test_plain_stardict_dictionary_can_be_looked_up() {
+    let dir = mk_workdir("stardict-plain");
+    let ifo = "bookname=Latin - German\nsametypesequence=m\nwebsite=https://freedict.org\n\
+            description=Test<br>dictionary\nauthor=Somebody\n";
+    write_stardict(&dir, ifo, &[("mater", b"Mutter"), ("Roma", b"Rom")], &[("matris", 0)],
+            32, false);
+    let mut dict = load_stardict(dir.join("test.ifo")).unwrap();
+    assert_eq!(dict.lookup("mater").unwrap(), "Mutter");
+    assert_eq!(dict.lookup("roma").unwrap(), "Rom");
+    assert_eq!(dict.lookup_with_headword("matris").unwrap(),
+               ("matris".to_string(), "Mutter".to_string()));
+    assert_eq!(dict.short_name().unwrap(), "Latin - German");
+    let info = dict.info().unwrap();
+    assert_eq!(info.url.as_deref(), Some("https://freedict.org"));
+    assert_eq!(info.info.as_deref(), Some("Test\ndictionary\nAuthor: Somebody"));
+    // the metadata is not expected in meta entries
+    assert!(!dict.validate().unwrap().iter()
+            .any(|i| matches!(i, dict::validation::Issue::MissingMetaEntry(_))));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_compressed_stardict_dictionary_with_64_bit_offsets() {
+    let dir = mk_workdir("stardict-64");
+    write_stardict(&dir, "bookname=Test\nsametypesequence=tm\n",
+            &[("rosa", b"'ro:sa\0Rose"), ("vinum", b"'vi:num\0Wein")], &[], 64, true);
+    let mut dict = load_stardict(dir.join("test.ifo")).unwrap();
+    assert_eq!(dict.lookup("vinum").unwrap(), "'vi:num\nWein");
+    assert_eq!(dict.headwords(), vec!["rosa", "vinum"]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_definitions_with_type_markers_keep_text_fields() {
+    let dir = mk_workdir("stardict-types");
+    write_stardict(&dir, "bookname=Test\n",
+            &[("rosa", b"mRose\0P\0\0\0\x02\x01\x02h<i>Rosa</i>\0")], &[], 32, false);
+    let mut dict = load_stardict(dir.join("test.ifo")).unwrap();
+    assert_eq!(dict.lookup("rosa").unwrap(), "Rose\n<i>Rosa</i>");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_binary_fields_need_not_be_valid_utf8() {
+    for &compress in [false, true].iter() {
+        let dir = mk_workdir("stardict-binary");
+        // the size of the picture (128 bytes) is not valid UTF-8 either
+        let mut typed = b"\0\0\0\x80".to_vec();
+        typed.extend_from_slice(&[0xff; 128]);
+        typed.extend_from_slice(b"Rose");
+        write_stardict(&dir, "bookname=Test\nsametypesequence=Pm\n", &[("rosa", &typed)], &[],
+                32, compress);
+        let mut dict = load_stardict(dir.join("test.ifo")).unwrap();
+        assert_eq!(dict.lookup("rosa").unwrap(), "Rose");
+
+        write_stardict(&dir, "bookname=Test\n",
+                &[("vinum", b"mWein\0W\0\0\0\x03\xff\xfe\x80h<i>vinum</i>\0")], &[], 32,
+                compress);
+        let mut dict = load_stardict(dir.join("test.ifo")).unwrap();
+        assert_eq!(dict.lookup("vinum").unwrap(), "Wein\n<i>vinum</i>");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn test_ifo_is_parsed() {
+    let ifo = "StarDict's dict ifo file\nversion=2.4.2\nbookname=Test\nwordcount=2\n\
+            synwordcount=1\nidxfilesize=30\nsametypesequence=m\ndate=2024.01.01\n";
+    let info = parse_ifo(ifo.as_bytes()).unwrap();
+    assert_eq!(info, StarDictInfo { version: "2.4.2".into(), bookname: "Test".into(),
+            word_count: 2, syn_word_count: 1, idx_file_size: 30, idx_offset_bits: 32,
+            same_type_sequence: Some("m".into()), date: Some("2024.01.01".into()),
+            ..StarDictInfo::default() });
+}
+
+#[test]
+fn test_invalid_files_are_reported() {
+    assert!(parse_ifo("version=3.0.0\n".as_bytes()).is_err());
+    assert!(parse_ifo("StarDict's dict ifo file\nwordcount=x\n".as_bytes()).is_err());
+    assert!(parse_idx(&b"rosa\0\0\0"[..], 32).is_err());
+    assert!(build_index(&[("rosa".into(), 0, 4)], &[("rose".into(), 1)]).is_err());
+
+    let dir = mk_workdir("stardict-missing");
+    fs::write(dir.join("test.ifo"), "StarDict's dict ifo file\nbookname=Test\n").unwrap();
+    assert!(load_stardict(dir.join("test.ifo")).is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_headwords_are_compared_like_stardict() {
+    assert_eq!(compare_headwords("abc", "ABD"), Ordering::Less);
+    assert_eq!(compare_headwords("Abc", "abc"), Ordering::Less);
+    assert_eq!(compare_headwords("abc", "abc"), Ordering::Equal);
+    assert_eq!(compare_headwords("zebra", "\u{e9}t\u{e9}"), Ordering::Less);
+    assert_eq!(compare_headwords("ab", "a_"), Ordering::Greater);
+}
+
+#[test]
+fn test_exported_dictionary_can_be_loaded() {
+    let mut builder = DictionaryBuilder::new();
+    builder.set_info(DatabaseInfo { short_name: Some("Latin - German".into()),
+            info: Some("Test\ndictionary".into()), url: Some("https://freedict.org".into()),
+            utf8: true, ..DatabaseInfo::default() });
+    builder.add_entry(&["mater", "matris", "Mutter"], "mater - Mutter").unwrap();
+    builder.add_entry(&["Roma"], "Rom").unwrap();
+    builder.add_entry(&["amo"], "lieben").unwrap();
+    let dir = mk_workdir("stardict-export");
+    export_stardict(&mut builder.build().unwrap(), dir.join("test.ifo")).unwrap();
+
+    let info = parse_ifo(fs::File::open(dir.join("test.ifo")).map(std::io::BufReader::new)
+            .unwrap()).unwrap();
+    assert_eq!((info.bookname.as_str(), info.word_count, info.syn_word_count),
+            ("Latin - German", 3, 2));
+    assert_eq!(info.description.as_deref(), Some("Test<br>dictionary"));
+    assert_eq!(info.idx_file_size, fs::metadata(dir.join("test.idx")).unwrap().len());
+    let entries = parse_idx(fs::File::open(dir.join("test.idx")).unwrap(), 32).unwrap();
+    let words: Vec<&str> = entries.iter().map(|e| e.0.as_str()).collect();
+    assert_eq!(words, vec!["amo", "mater", "Roma"]);
+    let synonyms = parse_syn(fs::File::open(dir.join("test.syn")).unwrap()).unwrap();
+    assert_eq!(synonyms, vec![("matris".to_string(), 1), ("Mutter".to_string(), 1)]);
+
+    let mut dict = load_stardict(dir.join("test.ifo")).unwrap();
+    assert_eq!(dict.lookup("mutter").unwrap(), "mater - Mutter\n");
+    assert_eq!(dict.lookup("roma").unwrap(), "Rom\n");
+    assert_eq!(dict.short_name().unwrap(), "Latin - German");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_only_headwords_sharing_a_definition_become_synonyms() {
+    // the definitions have the same text, but are stored separately
+    let mut builder = DictionaryBuilder::new();
+    builder.add_entry(&["lilium"], "Blume").unwrap();
+    builder.add_entry(&["rosa"], "Blume").unwrap();
+    let dir = mk_workdir("stardict-export-groups");
+    export_stardict(&mut builder.build().unwrap(), dir.join("test.ifo")).unwrap();
+    let info = parse_ifo(fs::File::open(dir.join("test.ifo")).map(std::io::BufReader::new)
+            .unwrap()).unwrap();
+    assert_eq!((info.word_count, info.syn_word_count), (2, 0));
+    let mut dict = load_stardict(dir.join("test.ifo")).unwrap();
+    assert_eq!(dict.lookup_with_headword("rosa").unwrap().0, "rosa");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_lat_deu_can_be_exported() {
+    let mut original = dict::load_dictionary_from_file(get_asset_path("lat-deu.dict.dz"),
+            get_asset_path("lat-deu.index")).unwrap();
+    let dir = mk_workdir("stardict-export-lat-deu");
+    export_stardict(&mut original, dir.join("lat-deu.ifo")).unwrap();
+    let entries = parse_idx(fs::File::open(dir.join("lat-deu.idx")).unwrap(), 32).unwrap();
+    assert!(entries.windows(2).all(|w| compare_headwords(&w[0].0, &w[1].0) != Ordering::Greater));
+
+    let mut exported = load_stardict(dir.join("lat-deu.ifo")).unwrap();
+    assert_eq!(exported.short_name().unwrap(), original.short_name().unwrap());
+    assert_eq!(exported.lookup("ferrugo").unwrap(), original.lookup("ferrugo").unwrap());
+    assert_eq!(exported.lookup_all("abitum").unwrap().len(), 2);
+    assert_eq!(exported.headwords(), original.headwords());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,116 @@
+use dict::*;
+use dict::errors::DictError;
+use dict::matching::{HeadwordIndex, MatchOptions};
+use dict::strategy::MatchStrategy;
+
+mod common;
+use common::example_dictionary;
+
+fn find(dict: &Dictionary, strategy: &str, query: &str) -> Vec<String> {
+    dict.match_words(strategy, query, &MatchOptions::default()).unwrap()
+}
+
+#[test]
+fn test_builtin_strategies_are_listed() {
+    let dict = example_dictionary();
+    let names: Vec<&str> = dict.strategies().list().into_iter().map(|(n, _)| n).collect();
+    for name in &["exact", "prefix", "suffix", "substring", "re", "glob", "lev", "soundex"] {
+        assert!(names.contains(name), "strategy {} missing", name);
+    }
+    assert!(dict.strategies().list().iter().all(|(_, descr)| !descr.is_empty()));
+}
+
+#[test]
+fn test_exact_strategy() {
+    let dict = example_dictionary();
+    assert_eq!(find(&dict, "exact", "amo"), vec!["amo"]);
+    assert!(find(&dict, "exact", "am").is_empty());
+}
+
+#[test]
+fn test_prefix_strategy() {
+    let dict = example_dictionary();
+    let matches = find(&dict, "prefix", "circumfer");
+    assert!(matches.contains(&"circumfero".to_string()));
+    assert!(matches.iter().all(|w| w.starts_with("circumfer")));
+}
+
+#[test]
+fn test_lev_strategy() {
+    let dict = example_dictionary();
+    let matches = find(&dict, "lev", "amor");
+    assert!(matches.contains(&"amo".to_string()));
+    assert!(matches.iter().all(|w| w.len() >= 3 && w.len() <= 5));
+}
+
+#[test]
+fn test_soundex_strategy() {
+    let dict = example_dictionary();
+    let matches = find(&dict, "soundex", "matir");
+    assert!(matches.contains(&"mater".to_string()));
+}
+
+#[test]
+fn test_unknown_strategy_is_reported() {
+    let dict = example_dictionary();
+    match dict.match_words("nonsense", "amo", &MatchOptions::default()) {
+        Err(DictError::UnknownStrategy(name)) => assert_eq!(name, "nonsense"),
+        other => panic!("expected UnknownStrategy, got {:?}", other),
+    }
+}
+
+/// strategy matching all headwords with the same first and last letter
+struct SameEnds;
+
+impl MatchStrategy for SameEnds {
+    fn name(&self) -> &str {
+        "sameends"
+    }
+
+    fn description(&self) -> &str {
+        "Match words with the same first and last letter as the query"
+    }
+
+    fn find(&self, query: &str, index: &HeadwordIndex, options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        let ends = |w: &str| (w.chars().next(), w.chars().last());
+        let candidates = index.headwords().iter().map(|w| w.as_str());
+        dict::matching::filter_headwords(candidates, options, |w| ends(w) == ends(query))
+    }
+}
+
+/// faulty strategy returning a headword which is not in the index
+struct Invented;
+
+impl MatchStrategy for Invented {
+    fn name(&self) -> &str {
+        "invented"
+    }
+
+    fn description(&self) -> &str {
+        "Match a word which does not exist"
+    }
+
+    fn find(&self, query: &str, _index: &HeadwordIndex, _options: &MatchOptions)
+            -> Result<Vec<String>, DictError> {
+        Ok(vec!["no such headword".into(), query.into()])
+    }
+}
+
+#[test]
+fn test_unknown_headwords_from_custom_strategies_are_skipped() {
+    let mut dict = example_dictionary();
+    dict.register_strategy(Box::new(Invented));
+    assert_eq!(find(&dict, "invented", "amo"), vec!["amo"]);
+}
+
+#[test]
+fn test_custom_strategies_can_be_registered() {
+    let mut dict = example_dictionary();
+    dict.register_strategy(Box::new(SameEnds));
+    assert!(dict.strategies().list().contains(&("sameends",
+            "Match words with the same first and last letter as the query")));
+    let matches = find(&dict, "sameends", "ago");
+    assert!(matches.contains(&"amo".to_string()));
+    assert!(matches.iter().all(|w| w.starts_with('a') && w.ends_with('o')));
+}
@@ -0,0 +1,132 @@
+#![cfg(feature = "tei")]
+
+use std::fs;
+
+use dict::*;
+use dict::errors::DictError;
+use dict::tei::*;
+
+mod common;
+use common::{get_asset_path, mk_workdir};
+
+static TEI: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<TEI xmlns="http://www.tei-c.org/ns/1.0">
+  <teiHeader>
+    <fileDesc>
+      <titleStmt><title>Latin - German FreeDict dictionary</title></titleStmt>
+      <editionStmt><edition>0.4</edition></editionStmt>
+      <publicationStmt><pubPlace><ref target="https://freedict.org"/></pubPlace></publicationStmt>
+      <notesStmt><note>Database Status: small</note></notesStmt>
+    </fileDesc>
+  </teiHeader>
+  <text><body>
+    <entry>
+      <form><orth>abdo</orth></form>
+      <form type="infl"><orth>abdere</orth></form>
+      <form type="infl"><orth>abdidi</orth></form>
+      <form type="infl"><orth>abditum</orth></form>
+      <gramGrp><num>sg</num><pos>v</pos></gramGrp>
+      <sense n="1"><cit type="trans"><quote>verbergen</quote></cit></sense>
+      <sense n="2"><cit type="trans"><quote>verstecken</quote></cit></sense>
+      <sense n="3"><cit type="trans"><quote>sich zurückziehen</quote></cit></sense>
+    </entry>
+    <entry>
+      <form><orth>abdere</orth></form>
+      <gramGrp><pos>v</pos></gramGrp>
+      <sense><note><ref target="#abdo">abdo</ref></note></sense>
+    </entry>
+    <entry>
+      <form><orth>mater</orth></form>
+      <form type="infl"><orth>matris</orth></form>
+      <gramGrp><pos>n</pos><num>sg</num><gen>f</gen></gramGrp>
+      <sense><cit type="trans"><quote>Mutter</quote></cit></sense>
+    </entry>
+    <entry>
+      <form><orth>Tecmessa</orth></form>
+      <form type="infl"><orth>Tecmessae</orth></form>
+      <gramGrp><pos>n</pos><num>sg</num><gen>f</gen></gramGrp>
+      <sense>
+        <def>mythische Geliebte des finsteren
+             griechischen Kämpfers Aias</def>
+        <cit type="trans"><quote>Tecmessa</quote></cit>
+      </sense>
+    </entry>
+    <entry>
+      <form><orth>rosa</orth><pron>ˈroːsa</pron></form>
+      <sense>
+        <usg type="dom">bot.</usg>
+        <cit type="trans"><quote>Rose</quote></cit>
+        <cit type="example"><quote>rosa &amp; lilium</quote>
+          <cit type="trans"><quote>Rose und Lilie</quote></cit></cit>
+      </sense>
+    </entry>
+  </body></text>
+</TEI>"##;
+
+#[test]
+fn test_entries_are_parsed() {
+    let (_, entries) = parse_tei(TEI.as_bytes()).unwrap();
+    assert_eq!(entries.len(), 5);
+    assert_eq!(entries[0].headwords, vec!["abdo"]);
+    assert_eq!(entries[0].inflections[2], Form { orth: "abditum".into(), grammar: vec![] });
+    assert_eq!(entries[0].grammar, vec!["sg", "v"]);
+    assert_eq!(entries[0].senses.len(), 3);
+    assert_eq!(entries[4].pronunciations, vec!["ˈroːsa"]);
+    assert_eq!(entries[4].senses[0].examples, vec![Example { quote: "rosa & lilium".into(),
+            translations: vec!["Rose und Lilie".into()] }]);
+}
+
+#[test]
+fn test_entries_are_rendered_like_freedict_databases() {
+    let mut latdeu = load_dictionary_from_file(get_asset_path("lat-deu.dict.dz"),
+            get_asset_path("lat-deu.index")).unwrap();
+    let (_, entries) = parse_tei(TEI.as_bytes()).unwrap();
+    for entry in &entries[..4] {
+        let expected = latdeu.lookup_all(&entry.headwords[0]).unwrap();
+        assert!(expected.contains(&render(entry)), "{:?} not in {:?}", render(entry), expected);
+    }
+    assert_eq!(render(&entries[4]),
+               "rosa /ˈroːsa/\n <bot.> Rose\n     \"rosa & lilium\" - Rose und Lilie\n");
+}
+
+#[test]
+fn test_header_is_converted_to_database_info() {
+    let (info, _) = parse_tei(TEI.as_bytes()).unwrap();
+    assert_eq!(info.short_name.as_deref(), Some("Latin - German FreeDict dictionary ver. 0.4"));
+    assert!(info.info.unwrap().contains("Database Status: small"));
+    assert_eq!(info.url.as_deref(), Some("https://freedict.org"));
+    assert!(info.utf8);
+}
+
+#[test]
+fn test_converted_files_can_be_loaded() {
+    let dir = mk_workdir("tei");
+    fs::write(dir.join("lat-deu.tei"), TEI).unwrap();
+    convert_file(dir.join("lat-deu.tei"), dir.join("lat-deu.dict.dz"), dir.join("lat-deu.index"))
+        .unwrap();
+    let mut dict = load_dictionary_from_file(dir.join("lat-deu.dict.dz"),
+            dir.join("lat-deu.index")).unwrap();
+    assert_eq!(dict.lookup("mater").unwrap(), "mater (matris) <n, sg, f>\n Mutter\n");
+    assert_eq!(dict.lookup_all("abdere").unwrap().len(), 1);
+    assert_eq!(dict.short_name().unwrap(), "Latin - German FreeDict dictionary ver. 0.4");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_invalid_xml_is_reported() {
+    assert!(parse_tei("<TEI><entry><form></entry>".as_bytes()).is_err());
+    assert!(parse_tei("<TEI><entry>".as_bytes()).is_err());
+}
+
+#[test]
+fn test_deeply_nested_elements_are_refused() {
+    let nested = |depth: usize| format!("<TEI><entry><form><orth>rosa</orth></form>{}{}</entry>\
+            </TEI>", "<sense>".repeat(depth), "</sense>".repeat(depth));
+    assert_eq!(parse_tei(nested(10).as_bytes()).unwrap().1.len(), 1);
+    match parse_tei(nested(100_000).as_bytes()) {
+        Err(DictError::InvalidFileFormat(explanation, _)) =>
+            assert!(explanation.contains("nested")),
+        Err(e) => panic!("expected InvalidFileFormat, got {:?}", e),
+        Ok(_) => panic!("deeply nested elements were accepted"),
+    }
+}
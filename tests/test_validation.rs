@@ -0,0 +1,95 @@
+use std::io::Cursor;
+
+use dict::*;
+use dict::dictreader::{DictReader, DictReaderRaw};
+use dict::errors::DictError;
+use dict::indexing::{parse_index, Location};
+use dict::validation::Issue;
+
+mod common;
+use common::{example_dictionary, mk_dict};
+
+// meta entries for a text starting with "short\ninfo\n"
+static META: &str = "00-database-short\tA\tG\n00-database-info\tG\tF\n";
+
+#[test]
+fn test_example_dictionary_has_no_issues() {
+    let mut dict = example_dictionary();
+    assert_eq!(dict.validate().unwrap(), vec![]);
+}
+
+#[test]
+fn test_missing_meta_entries_are_reported() {
+    let mut dict = mk_dict(b"short\nfoo\n", "foo\tG\tE");
+    let issues = dict.validate().unwrap();
+    assert!(issues.contains(&Issue::MissingMetaEntry("00-database-short".into())));
+    assert!(issues.contains(&Issue::MissingMetaEntry("00-database-info".into())));
+}
+
+#[test]
+fn test_out_of_range_entries_are_reported() {
+    let mut dict = mk_dict(b"short\ninfo\nfoo\n", &format!("{}foo\tL\tE\nbar\tN\tE", META));
+    assert_eq!(dict.validate().unwrap(),
+               vec![Issue::OutOfRange("bar".into(), Location::new(13, 4))]);
+}
+
+#[test]
+fn test_gaps_overlaps_and_duplicates_are_reported() {
+    let text = b"short\ninfo\nfoo\nfoobar\nxxxbaz\n";
+    let index = format!("{}foo\tL\tE\nfoobar\tP\tH\nfoob\tP\tH\nbar\tS\tE\nbaz\tZ\tE", META);
+    let issues = mk_dict(text, &index).validate().unwrap();
+    assert_eq!(issues, vec![
+        Issue::DuplicateSpan(vec!["foob".into(), "foobar".into()], Location::new(15, 7)),
+        Issue::HeadwordMismatch("foob".into(), Location::new(15, 7)),
+        Issue::Overlap("bar".into(), "foob".into(), Location::new(18, 4)),
+        Issue::Gap(22, 3),
+    ]);
+}
+
+#[test]
+fn test_invalid_utf8_is_reported() {
+    let mut dict = mk_dict(b"short\ninfo\nfoo\xff\n", &format!("{}foo\tL\tF", META));
+    assert_eq!(dict.validate().unwrap(),
+               vec![Issue::InvalidUtf8("foo".into(), Location::new(11, 5))]);
+}
+
+#[test]
+fn test_overflowing_spans_are_reported() {
+    // the offset is the largest number which can be represented
+    let mut dict = mk_dict(b"short\ninfo\n", &format!("{}foo\tP//////////\tB", META));
+    assert_eq!(dict.validate().unwrap(),
+               vec![Issue::OutOfRange("foo".into(), Location::new(u64::MAX, 1))]);
+}
+
+// reader which does not know the length of its data
+struct UnknownLength(DictReaderRaw<Cursor<Vec<u8>>>);
+
+impl DictReader for UnknownLength {
+    fn fetch_definition(&mut self, start_offset: u64, length: u64)
+            -> Result<String, errors::DictError> {
+        self.0.fetch_definition(start_offset, length)
+    }
+}
+
+#[test]
+fn test_readers_without_length_can_be_validated() {
+    let reader = DictReaderRaw::new(Cursor::new(b"short\ninfo\nfoo\n".to_vec())).unwrap();
+    let index = format!("{}foo\tL\tE\nbar\tP\tE", META);
+    let mut dict = load_dictionary(Box::new(UnknownLength(reader)),
+            parse_index(Cursor::new(index)).unwrap());
+    assert_eq!(dict.validate().unwrap(),
+               vec![Issue::OutOfRange("bar".into(), Location::new(15, 4))]);
+}
+
+#[test]
+fn test_unreadable_definitions_are_reported_and_validation_goes_on() {
+    // the length of "huge" is larger than any buffer allocated for a definition
+    let reader = DictReaderRaw::new(Cursor::new(b"short\ninfo\nfoo\nbar\n".to_vec())).unwrap();
+    let index = format!("{}foo\tL\tE\nhuge\tL\tBAAAA\nbad\tP\tE", META);
+    let mut dict = load_dictionary(Box::new(UnknownLength(reader)),
+            parse_index(Cursor::new(index)).unwrap());
+    let issues = dict.validate().unwrap();
+    assert!(issues.contains(&Issue::Unreadable("huge".into(), Location::new(11, 16777216),
+            DictError::MemoryError.to_string())), "{:?}", issues);
+    assert!(issues.contains(&Issue::HeadwordMismatch("bad".into(), Location::new(15, 4))));
+}